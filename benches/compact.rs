@@ -0,0 +1,146 @@
+//! Benchmarks for `compact` duration, and `read` latency before and after it
+//! Run using: `taskset -c 2,3,4,5 cargo bench --bench compact`
+
+use hdrhistogram::Histogram;
+use std::time;
+use tempfile::tempdir;
+use turbofox::{BufferSize, TurboFox, TurboFoxCfg};
+
+const OPS: usize = 0x40_000;
+
+/// Every n-th key is kept, all the others are deleted before compaction
+const KEEP_EVERY: usize = 2;
+
+const PAYLOAD_SIZE: usize = 0x20;
+const BATCH_SIZE: usize = 0x8000;
+const INITIAL_AVAILABLE_BUFFERS: usize = 0x400_000;
+
+#[derive(Debug)]
+struct BenchResult {
+    hist: Histogram<u64>,
+}
+
+#[inline]
+fn prep_init() -> (tempfile::TempDir, TurboFoxCfg) {
+    let dir = tempdir().unwrap();
+    let cfg = TurboFoxCfg {
+        buffer_size: BufferSize::S32,
+        initial_available_buffers: INITIAL_AVAILABLE_BUFFERS,
+        // NOTE: tombstones must pile up until the explicit compaction
+        tombstone_ratio: None,
+        ..TurboFoxCfg::new(dir.path())
+    };
+
+    (dir, cfg)
+}
+
+#[inline(always)]
+fn create_key(index: usize) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    let bytes = index.to_le_bytes();
+    let len = bytes.len().min(16);
+    key[..len].copy_from_slice(&bytes[..len]);
+    key
+}
+
+fn populate_engine(engine: &TurboFox, ops: usize) -> Vec<[u8; 16]> {
+    let mut keys = Vec::with_capacity(ops);
+    let mut last_ticket = None;
+
+    let payload = vec![0xAB; PAYLOAD_SIZE];
+
+    for i in 1..=ops {
+        let key = create_key(i);
+        let ticket = engine.write(&key, &payload).unwrap();
+        keys.push(key);
+
+        if i % BATCH_SIZE == 0 {
+            ticket.wait().unwrap();
+        }
+
+        last_ticket = Some(ticket);
+    }
+
+    if let Some(ticket) = last_ticket {
+        let _ = ticket.wait();
+    }
+
+    for (i, key) in keys.iter().enumerate() {
+        if i % KEEP_EVERY != 0 {
+            engine.delete(key).unwrap();
+        }
+    }
+
+    keys
+}
+
+/// Read all the keys, both the kept and the deleted ones, as misses pay the most for tombstones
+#[inline(always)]
+fn record_bench(engine: &TurboFox, keys: &[[u8; 16]]) -> BenchResult {
+    let mut hist = Histogram::<u64>::new(3).unwrap();
+
+    for key in keys {
+        let start = time::Instant::now();
+
+        let _ = engine.read(key).unwrap();
+
+        hist.record(start.elapsed().as_nanos() as u64).unwrap();
+    }
+
+    BenchResult { hist }
+}
+
+fn print_results(before: &BenchResult, after: &BenchResult, compact: time::Duration) {
+    println!();
+    println!("| Metric  | Before (µs)    | After (µs)    |");
+    println!("|:--------|:---------------|:--------------|");
+    println!(
+        "| P50     | {:>14.4} | {:>13.4} |",
+        before.hist.value_at_quantile(0.50) as f64 / 1000.0,
+        after.hist.value_at_quantile(0.50) as f64 / 1000.0,
+    );
+    println!(
+        "| P90     | {:>14.4} | {:>13.4} |",
+        before.hist.value_at_quantile(0.90) as f64 / 1000.0,
+        after.hist.value_at_quantile(0.90) as f64 / 1000.0,
+    );
+    println!(
+        "| P99     | {:>14.4} | {:>13.4} |",
+        before.hist.value_at_quantile(0.99) as f64 / 1000.0,
+        after.hist.value_at_quantile(0.99) as f64 / 1000.0,
+    );
+    println!(
+        "| MEAN    | {:>14.4} | {:>13.4} |",
+        before.hist.mean() / 1000.0,
+        after.hist.mean() / 1000.0,
+    );
+    println!(
+        "| MAX     | {:>14.4} | {:>13.4} |",
+        before.hist.max() as f64 / 1000.0,
+        after.hist.max() as f64 / 1000.0,
+    );
+    println!();
+    println!("Compaction took {:.4} ms", compact.as_secs_f64() * 1000.0);
+    println!();
+}
+
+fn main() {
+    let (_dir, cfg) = prep_init();
+    let engine = TurboFox::new(cfg).unwrap();
+
+    println!("-> Populating data ({OPS} ops, keeping 1 in {KEEP_EVERY} keys)...");
+    let keys = populate_engine(&engine, OPS);
+
+    println!("-> Running read benchmark before compaction...");
+    let before = record_bench(&engine, &keys);
+
+    println!("-> Compacting...");
+    let start = time::Instant::now();
+    engine.compact().unwrap();
+    let compact = start.elapsed();
+
+    println!("-> Running read benchmark after compaction...");
+    let after = record_bench(&engine, &keys);
+
+    print_results(&before, &after, compact);
+}
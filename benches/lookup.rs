@@ -0,0 +1,171 @@
+//! Benchmarks for index lookup latency on full pages, i.e. the worst case of the lane compares
+//! Run using: `taskset -c 2,3,4,5 cargo bench --bench lookup`
+//!
+//! Compare against the scalar compare w/
+//! `RUSTFLAGS="--cfg turbofox_scalar" cargo bench --bench lookup`
+
+use hdrhistogram::Histogram;
+use std::time;
+use tempfile::tempdir;
+use turbofox::{BufferSize, HashFn, TurboFox, TurboFoxCfg};
+
+const ITEMS_PER_PAGE: usize = 0x100;
+const PAGES: usize = 0x400;
+
+/// Only every other page of the first half is filled, so keys missing from a full page overflow
+/// into the empty page right after it
+const FULL_PAGES: usize = PAGES / 4;
+const OPS: usize = FULL_PAGES * ITEMS_PER_PAGE;
+
+const PAYLOAD_SIZE: usize = 0x20;
+const BATCH_SIZE: usize = 0x8000;
+const INITIAL_AVAILABLE_BUFFERS: usize = PAGES * ITEMS_PER_PAGE;
+
+#[derive(Debug)]
+struct BenchResult {
+    hist: Histogram<u64>,
+}
+
+/// The key carries its own hash, so every key lands in the page picked by [`create_key`]
+fn key_hash(_seed: u64, key: &[u8]) -> u64 {
+    u64::from_le_bytes(key[..8].try_into().unwrap())
+}
+
+#[inline]
+fn prep_init() -> (tempfile::TempDir, TurboFoxCfg) {
+    let dir = tempdir().unwrap();
+    let cfg = TurboFoxCfg {
+        buffer_size: BufferSize::S32,
+        initial_available_buffers: INITIAL_AVAILABLE_BUFFERS,
+        hash_fn: HashFn::Custom(key_hash),
+        ..TurboFoxCfg::new(dir.path())
+    };
+
+    (dir, cfg)
+}
+
+/// Key w/ the `nth` hash of the `full`-th full page, where the first [`ITEMS_PER_PAGE`] ones
+/// fill the page
+#[inline(always)]
+fn create_key(full: usize, nth: usize) -> [u8; 16] {
+    // NOTE: `nth + 1` keeps the hash clear of the reserved empty and tombstone hashes
+    let hash = (2 * full + PAGES * (nth + 1)) as u64;
+    let mut key = [0u8; 16];
+    key[..8].copy_from_slice(&hash.to_le_bytes());
+    key
+}
+
+fn populate_engine(engine: &TurboFox) -> (Vec<[u8; 16]>, Vec<[u8; 16]>) {
+    let mut hits = Vec::with_capacity(OPS);
+    let mut misses = Vec::with_capacity(OPS);
+    let mut last_ticket = None;
+
+    let payload = vec![0xAB; PAYLOAD_SIZE];
+
+    for nth in 0..ITEMS_PER_PAGE {
+        for full in 0..FULL_PAGES {
+            let key = create_key(full, nth);
+            let ticket = engine.write(&key, &payload).unwrap();
+            hits.push(key);
+            misses.push(create_key(full, ITEMS_PER_PAGE + nth));
+
+            if hits.len() % BATCH_SIZE == 0 {
+                ticket.wait().unwrap();
+            }
+
+            last_ticket = Some(ticket);
+        }
+    }
+
+    if let Some(ticket) = last_ticket {
+        let _ = ticket.wait();
+    }
+
+    (hits, misses)
+}
+
+#[inline(always)]
+fn record_bench<F: FnMut(&[u8; 16])>(keys: &[[u8; 16]], mut op: F) -> BenchResult {
+    let mut hist = Histogram::<u64>::new(3).unwrap();
+
+    for key in keys {
+        let start = time::Instant::now();
+
+        op(key);
+
+        hist.record(start.elapsed().as_nanos() as u64).unwrap();
+    }
+
+    BenchResult { hist }
+}
+
+fn print_results(hit: &BenchResult, miss: &BenchResult, absent: &BenchResult) {
+    println!();
+    println!("| Metric  | Read hit (µs) | Read miss (µs) | Write if absent (µs) |");
+    println!("|:--------|:--------------|:---------------|:---------------------|");
+    println!(
+        "| P50     | {:>13.4} | {:>14.4} | {:>20.4} |",
+        hit.hist.value_at_quantile(0.50) as f64 / 1000.0,
+        miss.hist.value_at_quantile(0.50) as f64 / 1000.0,
+        absent.hist.value_at_quantile(0.50) as f64 / 1000.0,
+    );
+    println!(
+        "| P90     | {:>13.4} | {:>14.4} | {:>20.4} |",
+        hit.hist.value_at_quantile(0.90) as f64 / 1000.0,
+        miss.hist.value_at_quantile(0.90) as f64 / 1000.0,
+        absent.hist.value_at_quantile(0.90) as f64 / 1000.0,
+    );
+    println!(
+        "| P99     | {:>13.4} | {:>14.4} | {:>20.4} |",
+        hit.hist.value_at_quantile(0.99) as f64 / 1000.0,
+        miss.hist.value_at_quantile(0.99) as f64 / 1000.0,
+        absent.hist.value_at_quantile(0.99) as f64 / 1000.0,
+    );
+    println!(
+        "| MEAN    | {:>13.4} | {:>14.4} | {:>20.4} |",
+        hit.hist.mean() / 1000.0,
+        miss.hist.mean() / 1000.0,
+        absent.hist.mean() / 1000.0,
+    );
+    println!(
+        "| MAX     | {:>13.4} | {:>14.4} | {:>20.4} |",
+        hit.hist.max() as f64 / 1000.0,
+        miss.hist.max() as f64 / 1000.0,
+        absent.hist.max() as f64 / 1000.0,
+    );
+    println!();
+}
+
+fn main() {
+    let compare = if cfg!(turbofox_scalar) {
+        "scalar"
+    } else if cfg!(all(target_arch = "x86_64", target_feature = "avx2")) {
+        "avx2"
+    } else if cfg!(target_arch = "x86_64") {
+        "sse2"
+    } else {
+        "scalar"
+    };
+
+    let (_dir, cfg) = prep_init();
+    let engine = TurboFox::new(cfg).unwrap();
+
+    println!("-> Lane compares: {compare}");
+    println!("-> Populating data ({FULL_PAGES} full pages)...");
+    let (hits, misses) = populate_engine(&engine);
+
+    println!("-> Running read hit benchmark...");
+    let hit = record_bench(&hits, |key| assert!(engine.read(key).unwrap().is_some()));
+
+    println!("-> Running read miss benchmark...");
+    let miss = record_bench(&misses, |key| assert!(engine.read(key).unwrap().is_none()));
+
+    // NOTE: each write probes its full home page before overflowing into the next one
+    println!("-> Running write if absent benchmark...");
+    let payload = vec![0xCD; PAYLOAD_SIZE];
+    let absent = record_bench(&misses, |key| {
+        assert!(engine.write_if_absent(key, &payload).unwrap().is_some())
+    });
+
+    print_results(&hit, &miss, &absent);
+}
@@ -0,0 +1,268 @@
+//! Benchmarks for mixed `read`/`write`/`delete` latency
+//! Run using: `taskset -c 2,3,4,5 cargo bench --bench mixed`
+//!
+//! The workload is configured w/ (optional) env vars:
+//!
+//! - `BENCH_MIX`: read/write/delete percentages, e.g. `50/30/20` (default)
+//! - `BENCH_KEYS`: number of distinct keys, e.g. `65536` (default)
+//! - `BENCH_VALUE_SIZE`: value size in bytes, fixed (`32`, default) or a uniform range (`8..512`)
+//! - `BENCH_KEY_DIST`: `uniform` (default) or `hot`, where 90% of ops hit 10% of keys
+
+use hdrhistogram::Histogram;
+use std::{sync, thread, time};
+use tempfile::tempdir;
+use turbofox::{BufferSize, TurboFox, TurboFoxCfg};
+
+const THREADS: usize = 4;
+const OPS: usize = 0x40_000;
+const OPS_PER_THREAD: usize = OPS / THREADS;
+const INITIAL_AVAILABLE_BUFFERS: usize = 0x400_000;
+
+#[derive(Debug)]
+struct BenchResult {
+    hist: Histogram<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeyDist {
+    Uniform,
+
+    /// 90% of ops hit the first 10% of keys
+    Hot,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Workload {
+    /// Op mix (in percent), remaining ops are deletes
+    read_pct: u64,
+    write_pct: u64,
+
+    /// Number of distinct keys touched by the workload
+    keys: usize,
+
+    /// Value sizes are uniformly distributed in `min_value..=max_value`
+    min_value: usize,
+    max_value: usize,
+
+    key_dist: KeyDist,
+}
+
+impl Workload {
+    fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok();
+        let num = |s: &str| s.trim().parse::<usize>().expect("invalid number in bench env var");
+
+        let (read_pct, write_pct) = match var("BENCH_MIX") {
+            Some(mix) => {
+                let pcts: Vec<usize> = mix.split('/').map(num).collect();
+                assert!(pcts.len() == 3 && pcts.iter().sum::<usize>() == 100, "invalid BENCH_MIX");
+
+                (pcts[0] as u64, pcts[1] as u64)
+            }
+
+            None => (50, 30),
+        };
+
+        let (min_value, max_value) = match var("BENCH_VALUE_SIZE") {
+            Some(size) => match size.split_once("..") {
+                Some((min, max)) => (num(min), num(max)),
+                None => (num(&size), num(&size)),
+            },
+
+            None => (0x20, 0x20),
+        };
+        assert!(min_value <= max_value, "invalid BENCH_VALUE_SIZE");
+
+        let key_dist = match var("BENCH_KEY_DIST").as_deref() {
+            None | Some("uniform") => KeyDist::Uniform,
+            Some("hot") => KeyDist::Hot,
+            Some(other) => panic!("invalid BENCH_KEY_DIST: {other}"),
+        };
+
+        Self {
+            read_pct,
+            write_pct,
+            keys: var("BENCH_KEYS").map_or(0x10_000, |keys| num(&keys)),
+            min_value,
+            max_value,
+            key_dist,
+        }
+    }
+
+    #[inline(always)]
+    fn key(&self, rng: &mut u64) -> [u8; 16] {
+        let hot = self.keys.div_ceil(10);
+
+        let idx = match self.key_dist {
+            KeyDist::Hot if rand(rng) % 10 != 0 => rand(rng) as usize % hot,
+            _ => rand(rng) as usize % self.keys,
+        };
+
+        create_key(idx)
+    }
+
+    #[inline(always)]
+    fn value_size(&self, rng: &mut u64) -> usize {
+        self.min_value + rand(rng) as usize % (self.max_value - self.min_value + 1)
+    }
+}
+
+#[inline]
+fn prep_init() -> (tempfile::TempDir, TurboFoxCfg) {
+    let dir = tempdir().unwrap();
+    let cfg = TurboFoxCfg {
+        buffer_size: BufferSize::S32,
+        initial_available_buffers: INITIAL_AVAILABLE_BUFFERS,
+        ..TurboFoxCfg::new(dir.path())
+    };
+
+    (dir, cfg)
+}
+
+#[inline(always)]
+fn create_key(index: usize) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    let bytes = index.to_le_bytes();
+    let len = bytes.len().min(16);
+    key[..len].copy_from_slice(&bytes[..len]);
+    key
+}
+
+#[inline(always)]
+fn rand(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+fn populate_engine(engine: &TurboFox, workload: &Workload) {
+    let payload = vec![0xAB; workload.max_value];
+    let mut last_ticket = None;
+    let mut rng = 0xC0FFEE;
+
+    for i in 0..workload.keys {
+        let size = workload.value_size(&mut rng);
+        last_ticket = Some(engine.write(&create_key(i), &payload[..size]).unwrap());
+    }
+
+    if let Some(ticket) = last_ticket {
+        let _ = ticket.wait();
+    }
+}
+
+#[inline(always)]
+fn record_bench(engine: &TurboFox, workload: &Workload, ops: usize, seed: u64) -> BenchResult {
+    let mut hist = Histogram::<u64>::new(3).unwrap();
+    let payload = vec![0xCD; workload.max_value];
+    let mut rng = seed;
+
+    for _ in 0..ops {
+        let key = workload.key(&mut rng);
+        let size = workload.value_size(&mut rng);
+        let op = rand(&mut rng) % 100;
+
+        let start = time::Instant::now();
+
+        if op < workload.read_pct {
+            let _ = engine.read(&key).unwrap();
+        } else if op < workload.read_pct + workload.write_pct {
+            let _ticket = engine.write(&key, &payload[..size]).unwrap();
+        } else {
+            engine.delete(&key).unwrap();
+        }
+
+        hist.record(start.elapsed().as_nanos() as u64).unwrap();
+    }
+
+    BenchResult { hist }
+}
+
+fn single_tx_mixed_latency(workload: Workload) -> BenchResult {
+    let (_dir, cfg) = prep_init();
+    let engine = TurboFox::new(cfg).unwrap();
+
+    println!("-> Populating single-thread data ({} keys)...", workload.keys);
+    populate_engine(&engine, &workload);
+
+    println!("-> Running single-thread mixed benchmark...");
+    record_bench(&engine, &workload, OPS, 0xDEADBEEFCAFEBABE)
+}
+
+fn multi_tx_mixed_latency(workload: Workload) -> BenchResult {
+    let (_dir, cfg) = prep_init();
+    let engine = sync::Arc::new(TurboFox::new(cfg).unwrap());
+
+    println!("-> Populating multi-thread data ({} keys)...", workload.keys);
+    populate_engine(&engine, &workload);
+
+    let barrier = sync::Arc::new(sync::Barrier::new(THREADS));
+
+    println!("-> Running multi-thread mixed benchmark...");
+    let mut handles = Vec::with_capacity(THREADS);
+
+    for tid in 0..THREADS {
+        let eng = sync::Arc::clone(&engine);
+        let barrier = sync::Arc::clone(&barrier);
+
+        handles.push(thread::spawn(move || {
+            let seed = 0xDEADBEEFCAFEBABE ^ ((tid as u64 + 1) << 32);
+
+            barrier.wait();
+            let result = record_bench(&eng, &workload, OPS_PER_THREAD, seed);
+            barrier.wait();
+
+            result
+        }));
+    }
+
+    let mut hist = Histogram::<u64>::new(3).unwrap();
+    for handle in handles {
+        let result = handle.join().unwrap();
+        hist.add(&result.hist).unwrap();
+    }
+
+    BenchResult { hist }
+}
+
+fn print_results(single: &BenchResult, multi: &BenchResult) {
+    println!();
+    println!("| Metric  | Single TX (µs) | Multi TX (µs) |");
+    println!("|:--------|:---------------|:--------------|");
+    println!(
+        "| P50     | {:>14.4} | {:>13.4} |",
+        single.hist.value_at_quantile(0.50) as f64 / 1000.0,
+        multi.hist.value_at_quantile(0.50) as f64 / 1000.0,
+    );
+    println!(
+        "| P90     | {:>14.4} | {:>13.4} |",
+        single.hist.value_at_quantile(0.90) as f64 / 1000.0,
+        multi.hist.value_at_quantile(0.90) as f64 / 1000.0,
+    );
+    println!(
+        "| P99     | {:>14.4} | {:>13.4} |",
+        single.hist.value_at_quantile(0.99) as f64 / 1000.0,
+        multi.hist.value_at_quantile(0.99) as f64 / 1000.0,
+    );
+    println!(
+        "| MEAN    | {:>14.4} | {:>13.4} |",
+        single.hist.mean() / 1000.0,
+        multi.hist.mean() / 1000.0,
+    );
+    println!(
+        "| MAX     | {:>14.4} | {:>13.4} |",
+        single.hist.max() as f64 / 1000.0,
+        multi.hist.max() as f64 / 1000.0,
+    );
+    println!();
+}
+
+fn main() {
+    let workload = Workload::from_env();
+    println!("-> Workload: {workload:?}");
+
+    let single = single_tx_mixed_latency(workload);
+    let multi = multi_tx_mixed_latency(workload);
+
+    print_results(&single, &multi);
+}
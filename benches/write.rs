@@ -25,11 +25,9 @@ struct BenchResult {
 fn prep_init() -> (tempfile::TempDir, TurboFoxCfg) {
     let dir = tempdir().expect("failed to create temp dir");
     let cfg = TurboFoxCfg {
-        path: dir.path().to_path_buf(),
         buffer_size: BufferSize::S32,
         initial_available_buffers: INITIAL_AVAILABLE_BUFFERS,
-        flush_duration: time::Duration::from_millis(2),
-        max_memory: 0x400 * 0x400 * 0x40, // 64 MB
+        ..TurboFoxCfg::new(dir.path())
     };
 
     (dir, cfg)
@@ -92,7 +90,7 @@ fn multi_tx_write_latency() -> BenchResult {
 
             // warmup
             for _ in 0..WARMUP_OPS {
-                let _ = eng.write(&warmup_key, &warmup_payload).expect("warmup write");
+                let _ticket = eng.write(&warmup_key, &warmup_payload).expect("warmup write");
             }
 
             barrier.wait();
@@ -135,8 +133,8 @@ fn print_results(single: &BenchResult, multi: &BenchResult) {
     );
     println!(
         "| MEAN    | {:>14.4} | {:>13.4} |",
-        single.hist.mean() as f64 / 1000.0,
-        multi.hist.mean() as f64 / 1000.0,
+        single.hist.mean() / 1000.0,
+        multi.hist.mean() / 1000.0,
     );
     println!(
         "| MAX     | {:>14.4} | {:>13.4} |",
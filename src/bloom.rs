@@ -0,0 +1,124 @@
+use std::sync::atomic;
+
+/// Bits reserved per expected entry (~1% false positives w/ [`PROBES`])
+const BITS_PER_ENTRY: usize = 0x0A;
+
+/// Number of bits set/tested for each hash
+const PROBES: u32 = 0x03;
+
+/// In-memory bloom filter over pre-computed 64-bit hashes
+///
+/// Bits are never cleared, so removed hashes stay (false) positives until the filter is rebuilt.
+#[derive(Debug)]
+pub(crate) struct Bloom {
+    words: Box<[atomic::AtomicU64]>,
+    mask: u64,
+}
+
+impl Bloom {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let n_bits = (capacity.max(1) * BITS_PER_ENTRY).next_power_of_two().max(0x40);
+        let words = (0..n_bits / 0x40).map(|_| atomic::AtomicU64::new(0)).collect();
+
+        Self {
+            words,
+            mask: (n_bits - 1) as u64,
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn insert(&self, hash: u64) {
+        for bit in self.bits(hash) {
+            let word = &self.words[(bit >> 6) as usize];
+            let flag = 1u64 << (bit & 0x3F);
+
+            if word.load(atomic::Ordering::Relaxed) & flag == 0 {
+                word.fetch_or(flag, atomic::Ordering::Relaxed);
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn contains(&self, hash: u64) -> bool {
+        self.bits(hash).all(|bit| {
+            let word = self.words[(bit >> 6) as usize].load(atomic::Ordering::Relaxed);
+            word & (1u64 << (bit & 0x3F)) != 0
+        })
+    }
+
+    /// Derive [`PROBES`] bit positions from `hash` using double hashing
+    ///
+    /// *NOTE:* `h2` is a full 64-bit remix of `hash`, as 32-bit hashes (widened to 64 bits) have no
+    /// high half to derive an independent step from.
+    #[inline(always)]
+    fn bits(&self, hash: u64) -> impl Iterator<Item = u64> + '_ {
+        let h1 = hash;
+        let h2 = remix(hash) | 1;
+
+        (0..PROBES).map(move |i| h1.wrapping_add((i as u64).wrapping_mul(h2)) & self.mask)
+    }
+}
+
+/// SplitMix64 finalizer, spreading every bit of `x` over all 64 bits
+#[inline(always)]
+fn remix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 0x1E)).wrapping_mul(0xBF58476D1CE4E5B9);
+    x = (x ^ (x >> 0x1B)).wrapping_mul(0x94D049BB133111EB);
+    x ^ (x >> 0x1F)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(i: u64) -> u64 {
+        twox_hash::XxHash64::oneshot(0, &i.to_le_bytes())
+    }
+
+    #[test]
+    fn ok_no_false_negatives() {
+        let bloom = Bloom::new(0x1000);
+
+        for i in 0..0x1000 {
+            bloom.insert(hash(i));
+        }
+
+        for i in 0..0x1000 {
+            assert!(bloom.contains(hash(i)));
+        }
+    }
+
+    #[test]
+    fn ok_empty_contains_nothing() {
+        let bloom = Bloom::new(0x100);
+
+        for i in 0..0x100 {
+            assert!(!bloom.contains(hash(i)));
+        }
+    }
+
+    #[test]
+    fn ok_low_false_positive_rate() {
+        let bloom = Bloom::new(0x1000);
+
+        for i in 0..0x1000 {
+            bloom.insert(hash(i));
+        }
+
+        let false_positives = (0x1000..0x2000).filter(|i| bloom.contains(hash(*i))).count();
+        assert!(false_positives < 0x1000 / 0x20, "too many false positives: {false_positives}");
+    }
+
+    #[test]
+    fn ok_low_false_positive_rate_w_32_bit_hashes() {
+        let hash = |i: u64| twox_hash::XxHash32::oneshot(0, &i.to_le_bytes()) as u64;
+        let bloom = Bloom::new(0x1000);
+
+        for i in 0..0x1000 {
+            bloom.insert(hash(i));
+        }
+
+        let false_positives = (0x1000..0x2000).filter(|i| bloom.contains(hash(*i))).count();
+        assert!(false_positives < 0x1000 / 0x20, "too many false positives: {false_positives}");
+    }
+}
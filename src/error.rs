@@ -0,0 +1,61 @@
+//! Error codes raised by [`TurboFox`](crate::TurboFox)
+//!
+//! Every error raised by the db is a [`FrozenError`], the codes in this module can be used to
+//! identify errors raised by [`TurboFox`](crate::TurboFox) itself (as opposed to errors raised by
+//! the underlying storage engine).
+//!
+//! ## Example
+//!
+//! ```
+//! use turbofox::error;
+//!
+//! assert_eq!(error::CPT.reason, 0x02);
+//! assert_eq!(error::CPT.detail, "data corruption");
+//! ```
+
+use crate::MODULE_ID;
+use frozen_core::error::{ErrCode, FrozenError, FrozenResult};
+
+/// Domain Id for [`TurboFox`](crate::TurboFox) is **1**
+pub const ERRDOMAIN: u8 = 0x01;
+
+/// stored entry failed checksum validation (e.g. torn or partial write)
+pub const CPT: ErrCode = ErrCode::new(0x02, "data corruption");
+
+/// provided config does not match the one persisted on disk
+pub const CFG: ErrCode = ErrCode::new(0x04, "config mismatch");
+
+/// every slot of the index is occupied, so no new key can be inserted
+pub const CAP: ErrCode = ErrCode::new(0x08, "capacity exhausted");
+
+/// provided config is invalid on its own (e.g. out of range values)
+pub const INV: ErrCode = ErrCode::new(0x10, "invalid config");
+
+/// db files do (or do not) exist, against the requested [`OpenMode`](crate::OpenMode)
+pub const OPN: ErrCode = ErrCode::new(0x20, "open mode violated");
+
+/// io error while managing the db files directly (e.g. on truncate)
+pub const IOE: ErrCode = ErrCode::new(0x40, "io error");
+
+/// Check if `err` was raised by [`TurboFox`](crate::TurboFox) w/ given `code`
+///
+/// ## Example
+///
+/// ```
+/// use turbofox::{error, ErrCode, FrozenError};
+///
+/// let err = FrozenError::new(0x02, error::ERRDOMAIN, error::CPT, "index page 0 slot 4");
+/// assert!(error::is_err(&err, &error::CPT));
+///
+/// let other = FrozenError::new(0x02, 0x12, ErrCode::new(0x02, "other"), "");
+/// assert!(!error::is_err(&other, &error::CPT));
+/// ```
+#[inline]
+pub fn is_err(err: &FrozenError, code: &ErrCode) -> bool {
+    err.module == MODULE_ID && err.domain == ERRDOMAIN && err.reason == code.reason
+}
+
+#[inline]
+pub(crate) fn new_err<R>(code: ErrCode, errmsg: &str) -> FrozenResult<R> {
+    Err(FrozenError::new(MODULE_ID, ERRDOMAIN, code, errmsg))
+}
@@ -0,0 +1,118 @@
+/// Hash function used to place keys in the index of [`TurboFox`](crate::TurboFox)
+///
+/// The chosen function is persisted when the db is created, and reopening w/ a different one
+/// fails w/ an [`error::CFG`](crate::error::CFG) error.
+///
+/// ## Example
+///
+/// ```
+/// use turbofox::HashFn;
+///
+/// assert!(matches!(HashFn::default(), HashFn::XxHash64));
+/// assert_ne!(HashFn::Fnv1a.hash(0, b"key"), HashFn::Fnv1a.hash(1, b"key"));
+///
+/// fn my_hash(seed: u64, key: &[u8]) -> u64 {
+///     key.iter().fold(seed, |acc, b| acc.rotate_left(5) ^ (*b as u64))
+/// }
+///
+/// let custom = HashFn::Custom(my_hash);
+/// assert_eq!(custom.hash(0, b"a"), my_hash(0, b"a"));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub enum HashFn {
+    /// 32-bit xxHash, widened to 64 bits
+    XxHash32,
+
+    /// 64-bit xxHash
+    #[default]
+    XxHash64,
+
+    /// 64-bit xxHash3
+    Xxh3,
+
+    /// 64-bit FNV-1a, seeded by mixing the seed into the offset basis
+    Fnv1a,
+
+    /// User supplied function, called w/ the per-db seed and the (zero-padded) key
+    ///
+    /// *NOTE:* Only the fact that a custom function is used is persisted, so the caller must
+    /// provide the exact same function on every open.
+    Custom(fn(u64, &[u8]) -> u64),
+}
+
+impl HashFn {
+    /// Hash `key` w/ given `seed`
+    #[inline(always)]
+    pub fn hash(&self, seed: u64, key: &[u8]) -> u64 {
+        match self {
+            Self::XxHash32 => twox_hash::XxHash32::oneshot(seed as u32, key) as u64,
+            Self::XxHash64 => twox_hash::XxHash64::oneshot(seed, key),
+            Self::Xxh3 => twox_hash::XxHash3_64::oneshot_with_seed(seed, key),
+            Self::Fnv1a => fnv1a(seed, key),
+            Self::Custom(f) => f(seed, key),
+        }
+    }
+
+    /// Stable identifier persisted in the meta file
+    #[inline]
+    pub(crate) fn tag(&self) -> u64 {
+        match self {
+            Self::XxHash32 => 0x01,
+            Self::XxHash64 => 0x02,
+            Self::Xxh3 => 0x03,
+            Self::Fnv1a => 0x04,
+            Self::Custom(_) => 0xFF,
+        }
+    }
+}
+
+#[inline(always)]
+fn fnv1a(seed: u64, key: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xCBF29CE484222325;
+    const PRIME: u64 = 0x00000100000001B3;
+
+    let mut hash = OFFSET_BASIS ^ seed;
+    for b in key {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(PRIME);
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ok_fnv1a_reference_vectors() {
+        assert_eq!(fnv1a(0, b""), 0xCBF29CE484222325);
+        assert_eq!(fnv1a(0, b"a"), 0xAF63DC4C8601EC8C);
+        assert_eq!(fnv1a(0, b"foobar"), 0x85944171F73967E8);
+    }
+
+    #[test]
+    fn ok_unique_tags() {
+        fn custom(_: u64, _: &[u8]) -> u64 {
+            0
+        }
+
+        let all = [
+            HashFn::XxHash32,
+            HashFn::XxHash64,
+            HashFn::Xxh3,
+            HashFn::Fnv1a,
+            HashFn::Custom(custom),
+        ];
+        let tags: std::collections::HashSet<u64> = all.iter().map(HashFn::tag).collect();
+
+        assert_eq!(tags.len(), 5);
+    }
+
+    #[test]
+    fn ok_seed_changes_hash() {
+        for hash_fn in [HashFn::XxHash32, HashFn::XxHash64, HashFn::Xxh3, HashFn::Fnv1a] {
+            assert_ne!(hash_fn.hash(1, b"key"), hash_fn.hash(2, b"key"));
+        }
+    }
+}
@@ -50,17 +50,10 @@
 //! ## Example
 //!
 //! ```
-//! use turbofox::{TurboFox, TurboFoxCfg, BufferSize};
-//! use std::time::Duration;
+//! use turbofox::{TurboFox, TurboFoxCfg};
 //!
 //! let dir = tempfile::tempdir().unwrap();
-//! let cfg = TurboFoxCfg {
-//!     path: dir.path().to_path_buf(),
-//!     buffer_size: BufferSize::S64,
-//!     initial_available_buffers: 0x1000,
-//!     flush_duration: Duration::from_millis(2),
-//!     max_memory: 0x400 * 0x400 * 0x40, // 64 MB
-//! };
+//! let cfg = TurboFoxCfg::new(dir.path());
 //!
 //! let db = TurboFox::new(cfg).unwrap();
 //!
@@ -80,12 +73,19 @@
 #![deny(unused_must_use)]
 #![allow(unsafe_op_in_unsafe_fn)]
 
+use frozen_core::ffile;
 use kosa::{Kosa, KosaCfg};
 use std::{path, time};
 
+pub mod error;
+mod bloom;
+mod hash;
 mod index;
+mod meta;
+mod simd;
 
-pub use frozen_core::error::{FrozenError, FrozenResult};
+pub use frozen_core::error::{ErrCode, FrozenError, FrozenResult};
+pub use hash::HashFn;
 pub use kosa::{AckTicket, BufferSize};
 
 /// Module ID used in [`frozen_core::error::FrozenError`]
@@ -93,33 +93,33 @@ pub(crate) const MODULE_ID: u8 = 0x02;
 
 /// All the available configurations for [`TurboFox`]
 ///
+/// Start from [`TurboFoxCfg::new`] and override only the fields which differ from the defaults.
+///
 /// ## Example
 ///
 /// ```
-/// use turbofox::{TurboFoxCfg, BufferSize};
-/// use std::time::Duration;
+/// use turbofox::TurboFoxCfg;
 ///
 /// let dir = tempfile::tempdir().unwrap();
-/// let cfg = TurboFoxCfg {
-///     path: dir.path().to_path_buf(),
-///     buffer_size: BufferSize::S64,
-///     initial_available_buffers: 0x1000,
-///     flush_duration: Duration::from_millis(2),
-///     max_memory: 0x400 * 0x400 * 0x40, // 64 MB
-/// };
+/// let cfg = TurboFoxCfg::new(dir.path());
 ///
 /// assert!(cfg.max_memory > 0);
 /// assert_eq!(cfg.buffer_size as usize, 0x40);
 /// ```
 #[derive(Debug, Clone)]
 pub struct TurboFoxCfg {
-    /// The root directory path where database files (`data` and `bmap`) will be stored
+    /// The root directory path where database files (`meta`, `index`, `data` and `bmap`) will be
+    /// stored
     pub path: path::PathBuf,
 
     /// Size (in bytes) of an individual page/buffer unit in the storage file
     pub buffer_size: BufferSize,
 
     /// Number of pre-allocated buffer slots in the internal bitmap tracker
+    ///
+    /// *NOTE:* Existing db files are never re-initialized, reopening w/ a smaller value keeps the
+    /// persisted capacity, while reopening w/ a larger value is refused w/ an [`error::CFG`]
+    /// error.
     pub initial_available_buffers: usize,
 
     /// Time interval used by the background `WritePipe` to perform a hard sync to the OS
@@ -127,25 +127,259 @@ pub struct TurboFoxCfg {
 
     /// Maximum allowed memory (in bytes) to be allocated simultaneously by the engine
     pub max_memory: usize,
+
+    /// Ratio (`0.0..=1.0`) of tombstones in an index page at which the page is compacted in place
+    ///
+    /// Set to `None` to disable auto compaction, pages can still be compacted manually w/
+    /// [`TurboFox::compact`]
+    pub tombstone_ratio: Option<f32>,
+
+    /// Keep an in-memory bloom filter over the index, so lookups of missing keys can skip the
+    /// index pages entirely
+    ///
+    /// The filter is rebuilt from the index on every open, costing a full scan of the index and
+    /// roughly 10 bits of memory per index slot.
+    pub bloom_filter: bool,
+
+    /// Hash function used to place keys in the index, see [`HashFn`]
+    pub hash_fn: HashFn,
+
+    /// How existing (or missing) db files at `path` are handled on open, see [`OpenMode`]
+    pub open_mode: OpenMode,
+
+    /// How much of an existing db is verified on open, see [`IntegrityCheck`]
+    pub integrity_check: IntegrityCheck,
 }
 
-/// TurboFox is a persistent and efficient embedded KV database
+/// Controls how much of the db is verified by [`TurboFox::new`], trading open latency for an
+/// early detection of corruption
+///
+/// Failed verifications are reported w/ an [`error::CPT`] error, naming the first invalid entry.
+/// The same scan can be run (and its progress observed) on an open db w/ [`TurboFox::verify`].
+///
+/// ## Example
+///
+/// ```
+/// use turbofox::{TurboFox, TurboFoxCfg, IntegrityCheck};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let cfg = TurboFoxCfg {
+///     integrity_check: IntegrityCheck::SampledPages(0.1),
+///     ..TurboFoxCfg::new(dir.path())
+/// };
+///
+/// let db = TurboFox::new(cfg).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum IntegrityCheck {
+    /// Only validate the `meta` file, which is done on every open regardless of this setting
+    #[default]
+    Headers,
+
+    /// Also verify the checksums of all the entries in (at least) the given ratio (`0.0..=1.0`)
+    /// of index pages, evenly spread across the index
+    SampledPages(f32),
+
+    /// Also verify the checksums of all the index entries
+    Full,
+}
+
+impl IntegrityCheck {
+    /// Distance between two verified index pages, or `None` if no page is verified
+    fn page_stride(&self) -> Option<usize> {
+        match self {
+            Self::Headers => None,
+            Self::SampledPages(ratio) if *ratio <= 0.0 => None,
+            Self::SampledPages(ratio) => Some((1.0 / ratio).floor().max(1.0) as usize),
+            Self::Full => Some(1),
+        }
+    }
+}
+
+/// Names of all the files owned by the db inside [`TurboFoxCfg::path`], where `meta` comes first
+const DB_FILES: [&str; 4] = ["meta", "index", "data", "bmap"];
+
+/// Controls how [`TurboFox::new`] treats the db files at [`TurboFoxCfg::path`]
+///
+/// A db is considered to exist when any of its files is present, so leftovers of a partially
+/// created (or removed) db are never silently adopted or overwritten. Violations are reported w/
+/// an [`error::OPN`] error.
 ///
 /// ## Example
 ///
 /// ```
-/// use turbofox::{TurboFox, TurboFoxCfg, BufferSize};
-/// use std::time::Duration;
+/// use turbofox::{error, TurboFox, TurboFoxCfg, OpenMode};
 ///
 /// let dir = tempfile::tempdir().unwrap();
 /// let cfg = TurboFoxCfg {
-///     path: dir.path().to_path_buf(),
-///     buffer_size: BufferSize::S64,
-///     initial_available_buffers: 0x1000,
-///     flush_duration: Duration::from_millis(2),
-///     max_memory: 0x400 * 0x400 * 0x40, // 64 MB
+///     open_mode: OpenMode::OpenExisting,
+///     ..TurboFoxCfg::new(dir.path())
 /// };
 ///
+/// let err = TurboFox::new(cfg).unwrap_err();
+/// assert!(error::is_err(&err, &error::OPN));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Create a new db, failing if one already exists
+    CreateNew,
+
+    /// Open an existing db, failing if there is none
+    OpenExisting,
+
+    /// Open the db if it exists, create it otherwise
+    #[default]
+    OpenOrCreate,
+
+    /// Remove any existing db files and create a fresh db
+    ///
+    /// Every existing file is locked exactly as an open db locks it before any of them is removed,
+    /// so truncating a db which is open anywhere else fails w/ [`error::OPN`] error.
+    Truncate,
+}
+
+impl OpenMode {
+    /// Enforce the mode against the db files at `path`, before any of them is opened
+    fn prepare(&self, path: &path::Path) -> FrozenResult<()> {
+        let exists = DB_FILES.iter().any(|name| path.join(name).exists());
+
+        match self {
+            Self::CreateNew if exists => {
+                let errmsg = format!("db already exists at {}", path.display());
+                error::new_err(error::OPN, &errmsg)
+            }
+
+            Self::OpenExisting if !exists => {
+                let errmsg = format!("no db exists at {}", path.display());
+                error::new_err(error::OPN, &errmsg)
+            }
+
+            Self::Truncate => {
+                // NOTE: the locks are held until all the files are removed, so none of them is
+                // pulled from under the mmaps of another open db
+                let mut locked = Vec::with_capacity(DB_FILES.len());
+
+                for name in DB_FILES.iter().filter(|name| path.join(name).exists()) {
+                    let file_cfg = ffile::FrozenFileCfg {
+                        module_id: MODULE_ID,
+                        buffer_size: 1,
+                        path: path.join(name),
+                        initial_available_buffers: 1,
+                    };
+
+                    match ffile::FrozenFile::new(file_cfg) {
+                        Ok(file) => locked.push(file),
+                        Err(err) => {
+                            let errmsg = format!("failed to lock {name}, db may be open: {err:?}");
+                            return error::new_err(error::OPN, &errmsg);
+                        }
+                    }
+                }
+
+                for name in DB_FILES {
+                    match std::fs::remove_file(path.join(name)) {
+                        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                            let errmsg = format!("failed to remove {name}: {err}");
+                            return error::new_err(error::IOE, &errmsg);
+                        }
+                        _ => {}
+                    }
+                }
+
+                drop(locked);
+                Ok(())
+            }
+
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for TurboFoxCfg {
+    /// Defaults for every field, where `path` is left empty (i.e. the current directory)
+    fn default() -> Self {
+        Self {
+            path: path::PathBuf::new(),
+            buffer_size: BufferSize::S64,
+            initial_available_buffers: 0x1000,
+            flush_duration: time::Duration::from_millis(2),
+            max_memory: 0x400 * 0x400 * 0x40, // 64 MB
+            tombstone_ratio: Some(0.25),
+            bloom_filter: false,
+            hash_fn: HashFn::default(),
+            open_mode: OpenMode::default(),
+            integrity_check: IntegrityCheck::default(),
+        }
+    }
+}
+
+impl TurboFoxCfg {
+    /// Create a config for the db at `path`, w/ every other field set to its [`Default`]
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use turbofox::{TurboFoxCfg, BufferSize};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let cfg = TurboFoxCfg {
+    ///     buffer_size: BufferSize::S128,
+    ///     ..TurboFoxCfg::new(dir.path())
+    /// };
+    ///
+    /// assert_eq!(cfg.path, dir.path());
+    /// assert_eq!(cfg.buffer_size as usize, 0x80);
+    /// assert_eq!(cfg.initial_available_buffers, 0x1000);
+    /// ```
+    pub fn new<P: Into<path::PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Reject values which can never work, before any db file is touched
+    fn validate(&self) -> FrozenResult<()> {
+        if self.initial_available_buffers == 0 {
+            return error::new_err(error::INV, "initial_available_buffers must be > 0");
+        }
+
+        if self.max_memory < self.buffer_size as usize {
+            let errmsg = format!(
+                "max_memory ({}) must fit at least one buffer ({})",
+                self.max_memory, self.buffer_size as usize
+            );
+            return error::new_err(error::INV, &errmsg);
+        }
+
+        if let Some(ratio) = self.tombstone_ratio {
+            if !(0.0..=1.0).contains(&ratio) {
+                let errmsg = format!("tombstone_ratio ({ratio}) must be in 0.0..=1.0");
+                return error::new_err(error::INV, &errmsg);
+            }
+        }
+
+        if let IntegrityCheck::SampledPages(ratio) = self.integrity_check {
+            if !(0.0..=1.0).contains(&ratio) {
+                let errmsg = format!("integrity_check ratio ({ratio}) must be in 0.0..=1.0");
+                return error::new_err(error::INV, &errmsg);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// TurboFox is a persistent and efficient embedded KV database
+///
+/// ## Example
+///
+/// ```
+/// use turbofox::{TurboFox, TurboFoxCfg};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let cfg = TurboFoxCfg::new(dir.path());
+///
 /// let db = TurboFox::new(cfg).unwrap();
 ///
 /// let key = b"my_key";
@@ -168,45 +402,98 @@ pub struct TurboFox {
 impl TurboFox {
     /// Creates or initializes a new [`TurboFox`] db instance
     ///
+    /// The geometry of the db (format version, `buffer_size`, `hash_fn` and the capacity) is
+    /// persisted on creation, and reopening w/ a mismatching [`TurboFoxCfg`] (or a larger
+    /// `initial_available_buffers`) fails w/ an [`error::CFG`] error, whose context names the
+    /// mismatching field along w/ its persisted and requested values.
+    ///
+    /// Db files at `path` w/o a valid `meta` file (e.g. written by an older version of this
+    /// crate) are never adopted, and are refused w/ an [`error::CFG`] error as well.
+    ///
+    /// Out of range values (e.g. `initial_available_buffers` of zero, or a `tombstone_ratio`
+    /// outside `0.0..=1.0`) are refused up front w/ an [`error::INV`] error.
+    ///
+    /// Existing db files at `path` are handled according to [`TurboFoxCfg::open_mode`], and then
+    /// verified according to [`TurboFoxCfg::integrity_check`].
+    ///
     /// ## Example
     ///
     /// ```
-    /// use turbofox::{TurboFox, TurboFoxCfg, BufferSize};
-    /// use std::time::Duration;
+    /// use turbofox::{TurboFox, TurboFoxCfg};
     ///
     /// let dir = tempfile::tempdir().unwrap();
-    /// let cfg = TurboFoxCfg {
-    ///     path: dir.path().to_path_buf(),
-    ///     buffer_size: BufferSize::S64,
-    ///     initial_available_buffers: 0x10,
-    ///     flush_duration: Duration::from_millis(0x0A),
-    ///     max_memory: 0x400 * 0x400,
-    /// };
+    /// let cfg = TurboFoxCfg::new(dir.path());
     ///
     /// let db = TurboFox::new(cfg).unwrap();
     /// ```
     pub fn new(cfg: TurboFoxCfg) -> FrozenResult<Self> {
+        cfg.validate()?;
+        cfg.open_mode.prepare(&cfg.path)?;
+
+        // NOTE: must be validated before any other file is touched w/ a mismatching config
+        let meta_path = cfg.path.join("meta");
+        let requested = meta::Meta::new(
+            cfg.buffer_size as usize,
+            index::ITEMS_PER_ROW,
+            cfg.hash_fn,
+            cfg.initial_available_buffers,
+        );
+        let persisted = meta::Meta::load(&meta_path, cfg.flush_duration)?;
+
+        match persisted {
+            Some(on_disk) => on_disk.validate(&requested)?,
+            None if DB_FILES[1..].iter().any(|name| cfg.path.join(name).exists()) => {
+                let errmsg = format!("legacy/unknown format: no meta at {}", cfg.path.display());
+                return error::new_err(error::CFG, &errmsg);
+            }
+            None => {}
+        }
+
+        // NOTE: the persisted capacity wins over a smaller requested one
+        let meta = persisted.unwrap_or(requested);
+        let initial_available_buffers = meta.initial_available_buffers as usize;
+
         let kosa_cfg = KosaCfg {
             path: cfg.path.clone(),
             buffer_size: cfg.buffer_size,
-            initial_available_buffers: cfg.initial_available_buffers,
+            initial_available_buffers,
             max_memory: cfg.max_memory,
             flush_duration: cfg.flush_duration,
         };
         let kosa = Kosa::new(kosa_cfg)?;
 
-        let init_pages = if cfg.initial_available_buffers < index::ITEMS_PER_ROW {
+        let init_pages = if initial_available_buffers < index::ITEMS_PER_ROW {
             1
         } else {
-            (cfg.initial_available_buffers + index::ITEMS_PER_ROW - 1) / index::ITEMS_PER_ROW
+            initial_available_buffers.div_ceil(index::ITEMS_PER_ROW)
         };
-        let index = index::Index::new(cfg.path.join("index"), init_pages, cfg.flush_duration)?;
+        let index = index::Index::new(index::IndexCfg {
+            path: cfg.path.join("index"),
+            init_pages,
+            flush_duration: cfg.flush_duration,
+            tombstone_ratio: cfg.tombstone_ratio,
+            bloom_filter: cfg.bloom_filter,
+            seed: meta.seed,
+            hash_fn: cfg.hash_fn,
+        })?;
+
+        // NOTE: persisted last, so a failure to open any other file never leaves a stray meta
+        if persisted.is_none() {
+            meta.persist(&meta_path, cfg.flush_duration)?;
+        }
 
-        Ok(Self { kosa, index })
+        let db = Self { kosa, index };
+        db.verify(cfg.integrity_check, |_, _| {})?;
+
+        Ok(db)
     }
 
     /// Writes a key-value pair into the database
     ///
+    /// If the index has no free slot left for a new key, an error w/ [`error::CAP`] code is
+    /// returned and the value is released from the storage engine (or leaked, if releasing it
+    /// fails as well).
+    ///
     /// ## Panics
     ///
     /// Panics in debug mode if the key length is greater than 16 bytes.
@@ -214,17 +501,10 @@ impl TurboFox {
     /// ## Example
     ///
     /// ```
-    /// use turbofox::{TurboFox, TurboFoxCfg, BufferSize};
-    /// use std::time::Duration;
+    /// use turbofox::{TurboFox, TurboFoxCfg};
     ///
     /// let dir = tempfile::tempdir().unwrap();
-    /// let db = TurboFox::new(TurboFoxCfg {
-    ///     path: dir.path().to_path_buf(),
-    ///     buffer_size: BufferSize::S64,
-    ///     initial_available_buffers: 0x10,
-    ///     flush_duration: Duration::from_millis(0x0A),
-    ///     max_memory: 0x400 * 0x400,
-    /// }).unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
     ///
     /// let ticket = db.write(b"user_1", b"alice").unwrap();
     /// ticket.wait().unwrap();
@@ -237,30 +517,217 @@ impl TurboFox {
         index_key[..key.len()].copy_from_slice(key);
 
         let (ticket, storage_id, n_buffers) = self.kosa.write(value)?;
-        self.index.write(index_key, storage_id, n_buffers)?;
+
+        if let Err(err) = self.index.write(index_key, storage_id, n_buffers) {
+            // NOTE: a failed release only leaks the buffers, the index error is returned either way
+            let _ = self.release(&ticket, &[(index_key, storage_id, n_buffers)]);
+            return Err(err);
+        }
+
+        Ok(ticket)
+    }
+
+    /// Writes a batch of key-value pairs into the database
+    ///
+    /// All the values are handed to the storage engine first, and only then the keys are published
+    /// into the index, so the index pages are not dirtied between value writes.
+    ///
+    /// Returns the [`AckTicket`] of the last write in the batch (or `None` for an empty batch),
+    /// waiting on it guarantees durability of the entire batch.
+    ///
+    /// If the index runs out of slots midway, an error w/ [`error::CAP`] code is returned, the keys
+    /// published before it stay written while the remaining values are released. A failing value
+    /// write releases all the values of the batch, w/o publishing any key.
+    ///
+    /// *NOTE:* Values are released after their writes have landed, and a failure to release them
+    /// only leaks their buffers, as the original error is always the one returned.
+    ///
+    /// ## Panics
+    ///
+    /// Panics in debug mode if any key length is greater than 16 bytes.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use turbofox::{TurboFox, TurboFoxCfg};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
+    ///
+    /// let batch: [(&[u8], &[u8]); 2] = [(b"user_1", b"alice"), (b"user_2", b"bob")];
+    /// db.write_batch(&batch).unwrap().unwrap().wait().unwrap();
+    ///
+    /// assert_eq!(db.read(b"user_2").unwrap().unwrap(), b"bob");
+    /// ```
+    pub fn write_batch(&self, pairs: &[(&[u8], &[u8])]) -> FrozenResult<Option<AckTicket>> {
+        let mut ticket = None;
+        let mut entries = Vec::with_capacity(pairs.len());
+
+        for (key, value) in pairs {
+            debug_assert!(key.len() <= 0x10, "key length must be <= 16");
+
+            let mut index_key = [0u8; 0x10];
+            index_key[..key.len()].copy_from_slice(key);
+
+            match self.kosa.write(value) {
+                Ok((tx, storage_id, n_buffers)) => {
+                    entries.push((index_key, storage_id, n_buffers));
+                    ticket = Some(tx);
+                }
+
+                Err(err) => {
+                    // NOTE: none of the keys is published yet, so all the values are released
+                    if let Some(ticket) = &ticket {
+                        let _ = self.release(ticket, &entries);
+                    }
+
+                    return Err(err);
+                }
+            }
+        }
+
+        for (i, (index_key, storage_id, n_buffers)) in entries.iter().enumerate() {
+            if let Err(err) = self.index.write(*index_key, *storage_id, *n_buffers) {
+                if let Some(ticket) = &ticket {
+                    let _ = self.release(ticket, &entries[i..]);
+                }
+
+                return Err(err);
+            }
+        }
 
         Ok(ticket)
     }
 
+    /// Writes a key-value pair into the database, only if the key does not exist yet
+    ///
+    /// Returns the [`AckTicket`] of the write, or `None` if the key already exists, in which case
+    /// the stored value is left untouched.
+    ///
+    /// The check and the write are atomic, racing writers of the same key are serialized by the
+    /// index lock of the key's row, so exactly one of them applies.
+    ///
+    /// *NOTE:* The key is checked before the value is handed to the storage engine, so a rejected
+    /// write costs only an index lookup. Only if a racing writer flips the condition in between,
+    /// the value is released again, which waits for its write to land first.
+    ///
+    /// ## Panics
+    ///
+    /// Panics in debug mode if the key length is greater than 16 bytes.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use turbofox::{TurboFox, TurboFoxCfg};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
+    ///
+    /// assert!(db.write_if_absent(b"user_1", b"alice").unwrap().is_some());
+    /// assert!(db.write_if_absent(b"user_1", b"bob").unwrap().is_none());
+    /// ```
+    pub fn write_if_absent(&self, key: &[u8], value: &[u8]) -> FrozenResult<Option<AckTicket>> {
+        self.write_if(key, value, index::WriteIf::Absent)
+    }
+
+    /// Writes a key-value pair into the database, only if the key already exists
+    ///
+    /// Returns the [`AckTicket`] of the write, or `None` if the key does not exist, in which case
+    /// nothing is written.
+    ///
+    /// The same atomicity and *NOTE* as for [`TurboFox::write_if_absent`] apply.
+    ///
+    /// *NOTE:* The buffers of the replaced value are released once the new value has landed, so
+    /// an applied write waits for its own write to land first.
+    ///
+    /// ## Panics
+    ///
+    /// Panics in debug mode if the key length is greater than 16 bytes.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use turbofox::{TurboFox, TurboFoxCfg};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
+    ///
+    /// assert!(db.write_if_present(b"user_1", b"alice").unwrap().is_none());
+    ///
+    /// db.write(b"user_1", b"alice").unwrap();
+    /// db.write_if_present(b"user_1", b"bob").unwrap().unwrap().wait().unwrap();
+    ///
+    /// assert_eq!(db.read(b"user_1").unwrap().unwrap(), b"bob");
+    /// ```
+    pub fn write_if_present(&self, key: &[u8], value: &[u8]) -> FrozenResult<Option<AckTicket>> {
+        self.write_if(key, value, index::WriteIf::Present)
+    }
+
+    fn write_if(
+        &self,
+        key: &[u8],
+        value: &[u8],
+        cond: index::WriteIf,
+    ) -> FrozenResult<Option<AckTicket>> {
+        debug_assert!(key.len() <= 0x10, "key length must be <= 16");
+
+        let mut index_key = [0u8; 0x10];
+        index_key[..key.len()].copy_from_slice(key);
+
+        if self.index.contains(index_key) != (cond == index::WriteIf::Present) {
+            return Ok(None);
+        }
+
+        let (ticket, storage_id, n_buffers) = self.kosa.write(value)?;
+        let entry = [(index_key, storage_id, n_buffers)];
+
+        match self.index.write_if(index_key, storage_id, n_buffers, cond) {
+            Ok(index::Written::Inserted) | Ok(index::Written::Replaced(None)) => Ok(Some(ticket)),
+
+            Ok(index::Written::Replaced(Some((old_id, old_n_buffers)))) => {
+                let old = [(index_key, old_id, old_n_buffers)];
+                self.release(&ticket, &old).map(|_| Some(ticket))
+            }
+
+            Ok(index::Written::Rejected) => self.release(&ticket, &entry).map(|_| None),
+
+            Err(err) => {
+                let _ = self.release(&ticket, &entry);
+                Err(err)
+            }
+        }
+    }
+
+    /// Hand the buffers of `entries` back to the storage engine, once their (possibly still in
+    /// flight) value writes covered by `ticket` have landed
+    ///
+    /// *NOTE:* Buffers released right away could be reused by a later write, and then be
+    /// overwritten by the stale in flight write.
+    fn release(&self, ticket: &AckTicket, entries: &[index::Entry]) -> FrozenResult<()> {
+        ticket.wait()?;
+
+        for (_, storage_id, n_buffers) in entries {
+            self.kosa.delete(*storage_id, *n_buffers as usize)?;
+        }
+
+        Ok(())
+    }
+
     /// Read the value associated w/ the key from the database
     ///
     /// Returns `Ok(Some(Vec<u8>))` if the key exists and the payload is successfully read, or
     /// `Ok(None)` if the key does not exist or fails validation in the storage engine.
     ///
+    /// If the index entry for the key fails checksum validation (e.g. was torn by a partial write),
+    /// an error w/ [`error::CPT`] code is returned instead of reading some unrelated value.
+    ///
     /// ## Example
     ///
     /// ```
-    /// use turbofox::{TurboFox, TurboFoxCfg, BufferSize};
-    /// use std::time::Duration;
+    /// use turbofox::{TurboFox, TurboFoxCfg};
     ///
     /// let dir = tempfile::tempdir().unwrap();
-    /// let db = TurboFox::new(TurboFoxCfg {
-    ///     path: dir.path().to_path_buf(),
-    ///     buffer_size: BufferSize::S64,
-    ///     initial_available_buffers: 0x10,
-    ///     flush_duration: Duration::from_millis(0x0A),
-    ///     max_memory: 0x400 * 0x400,
-    /// }).unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
     ///
     /// db.write(b"user_1", b"alice").unwrap().wait().unwrap();
     ///
@@ -287,17 +754,10 @@ impl TurboFox {
     /// ## Example
     ///
     /// ```
-    /// use turbofox::{TurboFox, TurboFoxCfg, BufferSize};
-    /// use std::time::Duration;
+    /// use turbofox::{TurboFox, TurboFoxCfg};
     ///
     /// let dir = tempfile::tempdir().unwrap();
-    /// let db = TurboFox::new(TurboFoxCfg {
-    ///     path: dir.path().to_path_buf(),
-    ///     buffer_size: BufferSize::S64,
-    ///     initial_available_buffers: 0x10,
-    ///     flush_duration: Duration::from_millis(0x0A),
-    ///     max_memory: 0x400 * 0x400,
-    /// }).unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
     ///
     /// db.write(b"temp_key", b"temporary data").unwrap().wait().unwrap();
     /// db.delete(b"temp_key").unwrap();
@@ -317,29 +777,344 @@ impl TurboFox {
 
         Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::time::Duration;
-
-    const INIT_BUFFERS: usize = 0x1000;
-    const MAX_MEMORY: usize = 64 * 1024 * 1024;
 
-    fn init() -> (tempfile::TempDir, TurboFox) {
-        let dir = tempfile::tempdir().expect("create tempdir");
-
-        let db = TurboFox::new(TurboFoxCfg {
-            path: dir.path().to_path_buf(),
-            buffer_size: BufferSize::S64,
-            initial_available_buffers: INIT_BUFFERS,
-            flush_duration: Duration::from_millis(1),
-            max_memory: MAX_MEMORY,
-        })
-        .expect("create db");
+    /// Iterate over all the keys stored in the database, in no particular order
+    ///
+    /// Only the index is read, values are never loaded from the storage engine. Keys are yielded
+    /// zero-padded to 16 bytes, exactly as they are stored in the index.
+    ///
+    /// A torn index entry is yielded as an [`error::CPT`] error (the same one [`TurboFox::read`]
+    /// returns for its key), after which the iteration goes on w/ the remaining keys.
+    ///
+    /// *NOTE:* The iterator reads one index page at a time, so concurrent writes and deletes may
+    /// or may not be observed by it.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use turbofox::{TurboFox, TurboFoxCfg};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
+    ///
+    /// db.write(b"user_1", b"alice").unwrap().wait().unwrap();
+    ///
+    /// let keys: Vec<[u8; 16]> = db.keys().collect::<Result<_, _>>().unwrap();
+    /// assert_eq!(keys.len(), 1);
+    /// assert_eq!(&keys[0][..6], b"user_1");
+    /// ```
+    pub fn keys(&self) -> Keys<'_> {
+        Keys {
+            index: &self.index,
+            page_idx: 0,
+            page_entries: Vec::new(),
+        }
+    }
 
-        (dir, db)
+    /// Iterate over all the key-value pairs for which `filter` returns `true`, in no particular
+    /// order
+    ///
+    /// `filter` is called w/ the (zero-padded) key and the number of buffers held by its value,
+    /// before the value is read, so filtered out entries never cost any value IO.
+    ///
+    /// Entries whose value is not (yet) readable from the storage engine are skipped, while torn
+    /// index entries are yielded as errors, and the same *NOTE* on concurrent writes as for
+    /// [`TurboFox::keys`] applies.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use turbofox::{TurboFox, TurboFoxCfg};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
+    ///
+    /// db.write(b"user_1", b"alice").unwrap();
+    /// db.write(b"order_1", b"pizza").unwrap().wait().unwrap();
+    ///
+    /// let users: Vec<_> = db.scan(|key, _| key.starts_with(b"user_")).collect();
+    /// assert_eq!(users.len(), 1);
+    ///
+    /// let (key, value) = users[0].as_ref().unwrap();
+    /// assert_eq!(&key[..6], b"user_1");
+    /// assert_eq!(value, b"alice");
+    /// ```
+    pub fn scan<F>(&self, filter: F) -> Scan<'_, F>
+    where
+        F: FnMut(&[u8; 0x10], usize) -> bool,
+    {
+        Scan {
+            kosa: &self.kosa,
+            keys: self.keys(),
+            filter,
+        }
+    }
+
+    /// Number of keys stored in the database
+    ///
+    /// A torn index entry fails the whole count w/ an [`error::CPT`] error, instead of silently
+    /// under-reporting it.
+    ///
+    /// *NOTE:* No counter is maintained, every call costs a full scan of the index, and the same
+    /// *NOTE* on concurrent writes as for [`TurboFox::keys`] applies.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use turbofox::{TurboFox, TurboFoxCfg};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
+    ///
+    /// assert!(db.is_empty().unwrap());
+    ///
+    /// db.write(b"user_1", b"alice").unwrap();
+    /// db.write(b"user_2", b"bob").unwrap();
+    ///
+    /// assert_eq!(db.len().unwrap(), 2);
+    /// ```
+    pub fn len(&self) -> FrozenResult<usize> {
+        (0..self.index.total_pages()).map(|p| self.index.page_occupancy(p)).sum()
+    }
+
+    /// Check if the database holds no keys, see [`TurboFox::len`]
+    pub fn is_empty(&self) -> FrozenResult<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Histogram of live keys per index row, to observe how evenly keys are spread
+    ///
+    /// The returned vec has one bucket per possible occupancy of a row (`0..=256` keys), holding
+    /// the number of rows w/ exactly that many keys. Heavily skewed rows fill up and spill into
+    /// their neighbours, making lookups slower.
+    ///
+    /// *NOTE:* Costs a full scan of the index and fails on torn entries, just like
+    /// [`TurboFox::len`].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use turbofox::{TurboFox, TurboFoxCfg};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
+    ///
+    /// db.write(b"user_1", b"alice").unwrap();
+    ///
+    /// let hist = db.row_occupancy_histogram().unwrap();
+    /// assert_eq!(hist.len(), 0x101);
+    /// assert_eq!(hist[1], 1);
+    /// ```
+    pub fn row_occupancy_histogram(&self) -> FrozenResult<Vec<usize>> {
+        let mut hist = vec![0; index::ITEMS_PER_ROW + 1];
+
+        for page_idx in 0..self.index.total_pages() {
+            hist[self.index.page_occupancy(page_idx)?] += 1;
+        }
+
+        Ok(hist)
+    }
+
+    /// Fault all the index pages into memory, so the first lookups after an open don't pay for
+    /// page faults
+    ///
+    /// `progress` is called after each index page w/ the number of pages warmed up so far and the
+    /// total number of pages. Values are not warmed up, as the storage engine owns their files.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use turbofox::{TurboFox, TurboFoxCfg};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
+    ///
+    /// let mut warmed = 0;
+    /// db.warmup(|done, total| {
+    ///     assert!(done <= total);
+    ///     warmed = done;
+    /// });
+    ///
+    /// assert!(warmed > 0);
+    /// ```
+    pub fn warmup<F: FnMut(usize, usize)>(&self, mut progress: F) {
+        let total = self.index.total_pages();
+
+        for page_idx in 0..total {
+            self.index.touch_page(page_idx);
+            progress(page_idx + 1, total);
+        }
+    }
+
+    /// Verify the index entries as selected by `check`, just like [`TurboFox::new`] does w/
+    /// [`TurboFoxCfg::integrity_check`]
+    ///
+    /// `progress` is called after each verified index page w/ the number of pages verified so far
+    /// and the total number of pages to verify, so long scans can be reported on. Opening w/
+    /// [`IntegrityCheck::Headers`] and verifying afterwards is the way to observe a scan on open.
+    ///
+    /// The first torn entry fails the scan w/ an [`error::CPT`] error, naming its page and slot.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use turbofox::{TurboFox, TurboFoxCfg, IntegrityCheck};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg::new(dir.path())).unwrap();
+    ///
+    /// let mut verified = 0;
+    /// db.verify(IntegrityCheck::Full, |done, total| {
+    ///     assert!(done <= total);
+    ///     verified = done;
+    /// })
+    /// .unwrap();
+    ///
+    /// assert!(verified > 0);
+    /// ```
+    pub fn verify<F: FnMut(usize, usize)>(
+        &self,
+        check: IntegrityCheck,
+        mut progress: F,
+    ) -> FrozenResult<()> {
+        let Some(stride) = check.page_stride() else {
+            return Ok(());
+        };
+
+        let total = self.index.total_pages().div_ceil(stride);
+
+        for (done, page_idx) in (0..self.index.total_pages()).step_by(stride).enumerate() {
+            self.index.verify_page(page_idx)?;
+            progress(done + 1, total);
+        }
+
+        Ok(())
+    }
+
+    /// Compact all the index pages by reclaiming slots held by tombstones of deleted keys
+    ///
+    /// Pages are compacted one at a time in place, so this is safe to call alongside other ops.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use turbofox::{TurboFox, TurboFoxCfg};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let db = TurboFox::new(TurboFoxCfg {
+    ///     tombstone_ratio: None,
+    ///     ..TurboFoxCfg::new(dir.path())
+    /// }).unwrap();
+    ///
+    /// db.write(b"a", b"one").unwrap();
+    /// db.write(b"b", b"two").unwrap().wait().unwrap();
+    /// db.delete(b"a").unwrap();
+    ///
+    /// db.compact().unwrap();
+    /// assert_eq!(db.read(b"b").unwrap(), Some(b"two".to_vec()));
+    /// ```
+    pub fn compact(&self) -> FrozenResult<()> {
+        self.index.compact()
+    }
+}
+
+/// Iterator over all the keys stored in [`TurboFox`], created w/ [`TurboFox::keys`]
+#[derive(Debug)]
+pub struct Keys<'a> {
+    index: &'a index::Index,
+    page_idx: usize,
+    page_entries: Vec<index::Entry>,
+}
+
+impl Keys<'_> {
+    fn next_entry(&mut self) -> Option<FrozenResult<index::Entry>> {
+        loop {
+            if let Some(entry) = self.page_entries.pop() {
+                return Some(Ok(entry));
+            }
+
+            if self.page_idx >= self.index.total_pages() {
+                return None;
+            }
+
+            // NOTE: valid entries of a page w/ torn ones are still yielded after the error
+            let res = self.index.page_entries(self.page_idx, &mut self.page_entries);
+            self.page_idx += 1;
+
+            if let Err(err) = res {
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+impl Iterator for Keys<'_> {
+    type Item = FrozenResult<[u8; 0x10]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().map(|entry| entry.map(|(key, _, _)| key))
+    }
+}
+
+/// Iterator over the filtered key-value pairs stored in [`TurboFox`], created w/
+/// [`TurboFox::scan`]
+pub struct Scan<'a, F> {
+    kosa: &'a Kosa,
+    keys: Keys<'a>,
+    filter: F,
+}
+
+impl<F> Iterator for Scan<'_, F>
+where
+    F: FnMut(&[u8; 0x10], usize) -> bool,
+{
+    type Item = FrozenResult<([u8; 0x10], Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(entry) = self.keys.next_entry() {
+            let (key, id, n_buffers) = match entry {
+                Ok(entry) => entry,
+                Err(err) => return Some(Err(err)),
+            };
+
+            if !(self.filter)(&key, n_buffers as usize) {
+                continue;
+            }
+
+            match self.kosa.read(id, n_buffers as usize) {
+                Ok(Some(value)) => return Some(Ok((key, value))),
+                Ok(None) => continue,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const INIT_BUFFERS: usize = 0x1000;
+    const MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+    fn cfg(dir: &tempfile::TempDir) -> TurboFoxCfg {
+        TurboFoxCfg {
+            initial_available_buffers: INIT_BUFFERS,
+            flush_duration: Duration::from_millis(1),
+            max_memory: MAX_MEMORY,
+            ..TurboFoxCfg::new(dir.path())
+        }
+    }
+
+    fn init() -> (tempfile::TempDir, TurboFox) {
+        let dir = tempfile::tempdir().expect("create tempdir");
+
+        let db = TurboFox::new(cfg(&dir)).expect("create db");
+
+        (dir, db)
     }
 
     fn key(id: u8) -> Vec<u8> {
@@ -405,6 +1180,40 @@ mod tests {
             assert_eq!(db.read(b"abc").unwrap(), Some(b"two".to_vec()));
         }
 
+        #[test]
+        fn ok_all_hash_fns() {
+            fn custom(seed: u64, key: &[u8]) -> u64 {
+                key.iter().fold(seed, |acc, b| acc.rotate_left(5) ^ (*b as u64))
+            }
+
+            let hash_fns = [
+                HashFn::XxHash32,
+                HashFn::XxHash64,
+                HashFn::Xxh3,
+                HashFn::Fnv1a,
+                HashFn::Custom(custom),
+            ];
+
+            for hash_fn in hash_fns {
+                let dir = tempfile::tempdir().expect("create tempdir");
+                let db = TurboFox::new(TurboFoxCfg {
+                    hash_fn,
+                    ..cfg(&dir)
+                })
+                .expect("create db");
+
+                let mut last = None;
+                for i in 0..0x80u8 {
+                    last = Some(db.write(&key(i), &[i]).unwrap());
+                }
+
+                last.unwrap().wait().unwrap();
+                for i in 0..0x80u8 {
+                    assert_eq!(db.read(&key(i)).unwrap(), Some(vec![i]));
+                }
+            }
+        }
+
         #[test]
         fn ok_variable_sizes() {
             let (_dir, db) = init();
@@ -421,6 +1230,221 @@ mod tests {
         }
     }
 
+    mod write_batch {
+        use super::*;
+
+        #[test]
+        fn ok_batch() {
+            let (_dir, db) = init();
+
+            let keys: Vec<Vec<u8>> = (0..0x80u8).map(key).collect();
+            let values: Vec<Vec<u8>> = (0..0x80u8).map(|i| vec![i; i as usize + 1]).collect();
+            let pairs: Vec<(&[u8], &[u8])> =
+                keys.iter().zip(values.iter()).map(|(k, v)| (k.as_slice(), v.as_slice())).collect();
+
+            db.write_batch(&pairs).unwrap().unwrap().wait().unwrap();
+
+            for (k, v) in keys.iter().zip(values.iter()) {
+                assert_eq!(db.read(k).unwrap().as_ref(), Some(v));
+            }
+        }
+
+        #[test]
+        fn ok_empty_batch() {
+            let (_dir, db) = init();
+
+            assert!(db.write_batch(&[]).unwrap().is_none());
+        }
+
+        #[test]
+        fn ok_duplicate_keys_last_wins() {
+            let (_dir, db) = init();
+
+            let batch: [(&[u8], &[u8]); 3] = [(b"a", b"one"), (b"b", b"two"), (b"a", b"three")];
+            db.write_batch(&batch).unwrap().unwrap().wait().unwrap();
+
+            assert_eq!(db.read(b"a").unwrap(), Some(b"three".to_vec()));
+            assert_eq!(db.read(b"b").unwrap(), Some(b"two".to_vec()));
+        }
+    }
+
+    mod write_if {
+        use super::*;
+
+        #[test]
+        fn ok_absent() {
+            let (_dir, db) = init();
+
+            db.write_if_absent(b"abc", b"one").unwrap().unwrap().wait().unwrap();
+            assert!(db.write_if_absent(b"abc", b"two").unwrap().is_none());
+
+            assert_eq!(db.read(b"abc").unwrap(), Some(b"one".to_vec()));
+        }
+
+        #[test]
+        fn ok_present() {
+            let (_dir, db) = init();
+
+            assert!(db.write_if_present(b"abc", b"one").unwrap().is_none());
+            assert_eq!(db.read(b"abc").unwrap(), None);
+
+            db.write(b"abc", b"one").unwrap();
+            db.write_if_present(b"abc", b"two").unwrap().unwrap().wait().unwrap();
+
+            assert_eq!(db.read(b"abc").unwrap(), Some(b"two".to_vec()));
+        }
+
+        #[test]
+        fn ok_rejected_writes_release_storage() {
+            let (_dir, db) = init();
+            db.write(b"abc", b"one").unwrap().wait().unwrap();
+
+            // would run out of buffers if rejected values were kept around
+            for _ in 0..INIT_BUFFERS * 2 {
+                assert!(db.write_if_absent(b"abc", b"two").unwrap().is_none());
+                assert!(db.write_if_present(b"xyz", b"two").unwrap().is_none());
+            }
+        }
+
+        #[test]
+        fn ok_absent_w_overflowed_key() {
+            fn collide(_seed: u64, _key: &[u8]) -> u64 {
+                0x10
+            }
+
+            for tombstone_ratio in [None, Some(0.0)] {
+                let dir = tempfile::tempdir().expect("create tempdir");
+                let db = TurboFox::new(TurboFoxCfg {
+                    initial_available_buffers: index::ITEMS_PER_ROW * 2,
+                    tombstone_ratio,
+                    hash_fn: HashFn::Custom(collide),
+                    ..cfg(&dir)
+                })
+                .unwrap();
+
+                // fills the home page, so the last key overflows into the next one
+                for i in 0..=index::ITEMS_PER_ROW {
+                    db.write(&(i as u64).to_le_bytes(), b"one").unwrap();
+                }
+
+                let overflowed = (index::ITEMS_PER_ROW as u64).to_le_bytes();
+                db.delete(&0u64.to_le_bytes()).unwrap();
+
+                assert!(db.write_if_absent(&overflowed, b"two").unwrap().is_none());
+                let keys: Vec<_> = db.keys().collect::<FrozenResult<_>>().unwrap();
+                assert_eq!(keys.iter().filter(|k| k[..8] == overflowed).count(), 1);
+
+                db.delete(&overflowed).unwrap();
+                assert_eq!(db.read(&overflowed).unwrap(), None);
+            }
+        }
+
+        #[test]
+        fn ok_concurrent_writes_of_overflowed_key_insert_once() {
+            fn collide(_seed: u64, _key: &[u8]) -> u64 {
+                0x10
+            }
+
+            let dir = tempfile::tempdir().expect("create tempdir");
+            let db = TurboFox::new(TurboFoxCfg {
+                initial_available_buffers: index::ITEMS_PER_ROW * 4,
+                tombstone_ratio: None,
+                hash_fn: HashFn::Custom(collide),
+                ..cfg(&dir)
+            })
+            .unwrap();
+
+            // fills the home page, so the last key overflows into the next one
+            for i in 0..=index::ITEMS_PER_ROW {
+                db.write(&(i as u64).to_le_bytes(), b"one").unwrap();
+            }
+
+            for i in 0..8u64 {
+                db.delete(&i.to_le_bytes()).unwrap();
+            }
+
+            // all the writers race on the same new keys, while the freed home slots are claimed
+            let barrier = std::sync::Barrier::new(8);
+
+            std::thread::scope(|s| {
+                for i in 0..8u8 {
+                    let (db, barrier) = (&db, &barrier);
+
+                    s.spawn(move || {
+                        barrier.wait();
+
+                        for id in 0x1000..0x1040u64 {
+                            db.write(&id.to_le_bytes(), &[i]).unwrap();
+                        }
+                    });
+                }
+            });
+
+            let keys: Vec<_> = db.keys().collect::<FrozenResult<_>>().unwrap();
+
+            for id in 0x1000..0x1040u64 {
+                let new = id.to_le_bytes();
+                assert_eq!(keys.iter().filter(|k| k[..8] == new).count(), 1);
+            }
+
+            db.delete(&0x1000u64.to_le_bytes()).unwrap();
+            assert_eq!(db.read(&0x1000u64.to_le_bytes()).unwrap(), None);
+        }
+
+        #[test]
+        fn ok_present_releases_replaced_values() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+            let db = TurboFox::new(TurboFoxCfg {
+                initial_available_buffers: 0x100,
+                ..cfg(&dir)
+            })
+            .unwrap();
+
+            db.write(b"abc", b"zero").unwrap().wait().unwrap();
+
+            // would run out of buffers if the replaced values were kept around
+            for i in 0..0x800u16 {
+                let value = i.to_le_bytes();
+                db.write_if_present(b"abc", &value).unwrap().unwrap();
+
+                assert_eq!(db.read(b"abc").unwrap(), Some(value.to_vec()));
+            }
+        }
+
+        #[test]
+        fn ok_rejected_writes_never_clobber_reused_buffers() {
+            let (_dir, db) = init();
+            db.write(b"abc", b"one").unwrap().wait().unwrap();
+
+            // released buffers are reused right away by the next write
+            for i in 0..0x400u16 {
+                let value = vec![i as u8; 0x10];
+                assert!(db.write_if_absent(b"abc", &[0xFF; 0x20]).unwrap().is_none());
+
+                db.write(b"xyz", &value).unwrap().wait().unwrap();
+                assert_eq!(db.read(b"xyz").unwrap(), Some(value));
+            }
+        }
+
+        #[test]
+        fn ok_concurrent_absent_applies_once() {
+            let (_dir, db) = init();
+
+            let applied: usize = std::thread::scope(|s| {
+                let handles: Vec<_> = (0..8u8)
+                    .map(|i| {
+                        let db = &db;
+                        s.spawn(move || db.write_if_absent(b"abc", &[i]).unwrap().is_some())
+                    })
+                    .collect();
+
+                handles.into_iter().map(|h| h.join().unwrap() as usize).sum()
+            });
+
+            assert_eq!(applied, 1);
+        }
+    }
+
     mod delete {
         use super::*;
 
@@ -466,35 +1490,748 @@ mod tests {
         }
     }
 
-    mod persistence {
+    mod keys {
         use super::*;
 
+        fn padded(key: &[u8]) -> [u8; 0x10] {
+            let mut padded = [0u8; 0x10];
+            padded[..key.len()].copy_from_slice(key);
+            padded
+        }
+
         #[test]
-        fn ok_reopen() {
-            let dir = tempfile::tempdir().expect("create tempdir");
+        fn ok_empty() {
+            let (_dir, db) = init();
 
-            let cfg = TurboFoxCfg {
-                path: dir.path().to_path_buf(),
-                buffer_size: BufferSize::S64,
-                initial_available_buffers: INIT_BUFFERS,
-                flush_duration: Duration::from_millis(1),
-                max_memory: MAX_MEMORY,
-            };
+            assert_eq!(db.keys().count(), 0);
+        }
 
-            {
-                let db = TurboFox::new(cfg.clone()).unwrap();
+        #[test]
+        fn ok_all_keys() {
+            let (_dir, db) = init();
+            let mut last = None;
 
-                db.write(b"a", b"one").unwrap();
-                db.write(b"b", b"two").unwrap();
+            for i in 0..0x80u8 {
+                last = Some(db.write(&key(i), &[i]).unwrap());
             }
 
-            {
-                let db = TurboFox::new(cfg).unwrap();
+            last.unwrap().wait().unwrap();
+            db.delete(&key(0x10)).unwrap();
+
+            let mut keys: Vec<_> = db.keys().collect::<FrozenResult<_>>().unwrap();
+            keys.sort();
+
+            let expected: Vec<_> =
+                (0..0x80u8).filter(|i| *i != 0x10).map(|i| padded(&key(i))).collect();
+            assert_eq!(keys, expected);
+        }
+    }
+
+    mod occupancy {
+        use super::*;
+
+        #[test]
+        fn ok_len() {
+            let (_dir, db) = init();
+            assert!(db.is_empty().unwrap());
+
+            for i in 0..0x80u8 {
+                db.write(&key(i), &[i]).unwrap();
+            }
+
+            db.write(&key(0), b"overwrite").unwrap();
+            db.delete(&key(1)).unwrap();
+
+            assert_eq!(db.len().unwrap(), 0x7F);
+            assert_eq!(db.len().unwrap(), db.keys().count());
+        }
+
+        #[test]
+        fn ok_row_occupancy_histogram() {
+            let (_dir, db) = init();
+
+            for i in 0..0x80u8 {
+                db.write(&key(i), &[i]).unwrap();
+            }
+
+            let hist = db.row_occupancy_histogram().unwrap();
+            let rows = INIT_BUFFERS / index::ITEMS_PER_ROW;
+
+            assert_eq!(hist.len(), index::ITEMS_PER_ROW + 1);
+            assert_eq!(hist.iter().sum::<usize>(), rows);
+
+            let keys: usize = hist.iter().enumerate().map(|(n, rows)| n * rows).sum();
+            assert_eq!(keys, 0x80);
+        }
+    }
+
+    mod torn {
+        use super::*;
+
+        fn init_torn() -> (tempfile::TempDir, TurboFox) {
+            let (dir, db) = init();
+
+            db.write(&key(1), b"one").unwrap();
+            db.write(&key(2), b"two").unwrap().wait().unwrap();
+
+            let mut torn = [0u8; 0x10];
+            torn[0] = 1;
+            db.index.tear_entry(torn);
+
+            (dir, db)
+        }
+
+        #[test]
+        fn err_keys() {
+            let (_dir, db) = init_torn();
+            let keys: Vec<_> = db.keys().collect();
+
+            assert_eq!(keys.len(), 2);
+            assert!(keys.iter().any(|k| k.as_ref().is_err_and(|e| error::is_err(e, &error::CPT))));
+            assert!(keys.iter().any(|k| k.as_ref().is_ok_and(|k| k[0] == 2)));
+        }
+
+        #[test]
+        fn err_scan() {
+            let (_dir, db) = init_torn();
+            let pairs: Vec<_> = db.scan(|_, _| true).collect();
+
+            assert_eq!(pairs.len(), 2);
+            assert!(pairs.iter().any(|p| p.as_ref().is_err_and(|e| error::is_err(e, &error::CPT))));
+        }
+
+        #[test]
+        fn err_len() {
+            let (_dir, db) = init_torn();
+
+            assert!(error::is_err(&db.len().unwrap_err(), &error::CPT));
+            assert!(error::is_err(&db.is_empty().unwrap_err(), &error::CPT));
+            assert!(error::is_err(&db.row_occupancy_histogram().unwrap_err(), &error::CPT));
+            assert!(error::is_err(&db.read(&key(1)).unwrap_err(), &error::CPT));
+        }
+    }
+
+    mod scan {
+        use super::*;
+
+        #[test]
+        fn ok_filter_by_key() {
+            let (_dir, db) = init();
+            let mut last = None;
+
+            for i in 0..0x80u8 {
+                last = Some(db.write(&key(i), &[i]).unwrap());
+            }
+
+            last.unwrap().wait().unwrap();
+
+            let mut pairs: Vec<_> = db.scan(|k, _| k[0] % 2 == 0).map(Result::unwrap).collect();
+            pairs.sort();
+
+            assert_eq!(pairs.len(), 0x40);
+            for (k, v) in pairs {
+                assert_eq!(k[0] % 2, 0);
+                assert_eq!(v, vec![k[0]]);
+            }
+        }
+
+        #[test]
+        fn ok_filter_by_n_buffers() {
+            let (_dir, db) = init();
+
+            db.write(b"small", &[0xAA; 0x10]).unwrap();
+            db.write(b"large", &[0xBB; 0x40 * 3]).unwrap().wait().unwrap();
+
+            let large: Vec<_> = db.scan(|_, n_buffers| n_buffers > 1).map(Result::unwrap).collect();
+
+            assert_eq!(large.len(), 1);
+            assert_eq!(&large[0].0[..5], b"large");
+            assert_eq!(large[0].1, vec![0xBB; 0x40 * 3]);
+        }
+
+        #[test]
+        fn ok_filter_skips_value_reads() {
+            let (_dir, db) = init();
+            let mut seen = 0;
+
+            for i in 0..0x10u8 {
+                db.write(&key(i), &[i]).unwrap().wait().unwrap();
+            }
+
+            db.delete(&key(0)).unwrap();
+
+            let count = db
+                .scan(|_, _| {
+                    seen += 1;
+                    false
+                })
+                .count();
+
+            assert_eq!(count, 0);
+            assert_eq!(seen, 0x0F);
+        }
+    }
+
+    mod persistence {
+        use super::*;
+
+        #[test]
+        fn ok_reopen() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            let cfg = cfg(&dir);
+
+            {
+                let db = TurboFox::new(cfg.clone()).unwrap();
+
+                db.write(b"a", b"one").unwrap();
+                db.write(b"b", b"two").unwrap();
+            }
+
+            {
+                let db = TurboFox::new(cfg).unwrap();
 
                 assert_eq!(db.read(b"a").unwrap(), Some(b"one".to_vec()));
                 assert_eq!(db.read(b"b").unwrap(), Some(b"two".to_vec()));
             }
         }
+
+        #[test]
+        fn ok_reopen_w_smaller_capacity() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            let cfg = cfg(&dir);
+
+            {
+                let db = TurboFox::new(cfg.clone()).unwrap();
+                let mut last = None;
+
+                for i in 0..0x80u8 {
+                    last = Some(db.write(&key(i), &[i]).unwrap());
+                }
+
+                last.unwrap().wait().unwrap();
+            }
+
+            {
+                // persisted geometry wins over the requested one, so no entry is lost
+                let db = TurboFox::new(TurboFoxCfg {
+                    initial_available_buffers: INIT_BUFFERS / 0x10,
+                    ..cfg
+                })
+                .unwrap();
+
+                for i in 0..0x80u8 {
+                    assert_eq!(db.read(&key(i)).unwrap(), Some(vec![i]));
+                }
+            }
+        }
+
+        #[test]
+        fn err_reopen_w_different_buffer_size() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            let cfg = cfg(&dir);
+
+            {
+                let db = TurboFox::new(cfg.clone()).unwrap();
+                db.write(b"a", b"one").unwrap().wait().unwrap();
+            }
+
+            let err = TurboFox::new(TurboFoxCfg {
+                buffer_size: BufferSize::S128,
+                ..cfg.clone()
+            })
+            .unwrap_err();
+            assert!(error::is_err(&err, &error::CFG));
+
+            let db = TurboFox::new(cfg).unwrap();
+            assert_eq!(db.read(b"a").unwrap(), Some(b"one".to_vec()));
+        }
+
+        #[test]
+        fn err_reopen_w_larger_capacity() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            let cfg = cfg(&dir);
+
+            {
+                let db = TurboFox::new(cfg.clone()).unwrap();
+                db.write(b"a", b"one").unwrap().wait().unwrap();
+            }
+
+            // files are never silently re-initialized, growing them is refused instead
+            let err = TurboFox::new(TurboFoxCfg {
+                initial_available_buffers: INIT_BUFFERS * 0x10,
+                ..cfg.clone()
+            })
+            .unwrap_err();
+            assert!(error::is_err(&err, &error::CFG));
+
+            let errmsg = format!(
+                "initial_available_buffers: on disk {INIT_BUFFERS}, requested {}",
+                INIT_BUFFERS * 0x10
+            );
+            assert!(err.context.contains(&errmsg));
+
+            let db = TurboFox::new(cfg).unwrap();
+            assert_eq!(db.read(b"a").unwrap(), Some(b"one".to_vec()));
+        }
+
+        #[test]
+        fn err_open_legacy_db() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            {
+                let db = TurboFox::new(cfg(&dir)).unwrap();
+                db.write(b"a", b"one").unwrap().wait().unwrap();
+            }
+
+            // db files w/o meta, i.e. the layout of the versions before meta was introduced
+            std::fs::remove_file(dir.path().join("meta")).unwrap();
+
+            let err = TurboFox::new(cfg(&dir)).unwrap_err();
+            assert!(error::is_err(&err, &error::CFG));
+            assert!(err.context.contains("legacy/unknown format"));
+            assert!(!dir.path().join("meta").exists());
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn err_no_stray_meta_on_failed_open() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            // NOTE: a dangling symlink doesn't count as an existing file, but can't be created
+            let missing = dir.path().join("missing").join("index");
+            std::os::unix::fs::symlink(missing, dir.path().join("index")).unwrap();
+
+            assert!(TurboFox::new(cfg(&dir)).is_err());
+            assert!(!dir.path().join("meta").exists());
+        }
+    }
+
+    mod config {
+        use super::*;
+
+        fn assert_inv(cfg: TurboFoxCfg) {
+            let path = cfg.path.clone();
+            let err = TurboFox::new(cfg).unwrap_err();
+
+            assert!(error::is_err(&err, &error::INV));
+            assert!(!path.join("meta").exists());
+        }
+
+        #[test]
+        fn ok_edge_tombstone_ratios() {
+            for ratio in [None, Some(0.0), Some(1.0)] {
+                let dir = tempfile::tempdir().expect("create tempdir");
+                let cfg = TurboFoxCfg {
+                    tombstone_ratio: ratio,
+                    ..cfg(&dir)
+                };
+
+                assert!(TurboFox::new(cfg).is_ok());
+            }
+        }
+
+        #[test]
+        fn err_zero_buffers() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            assert_inv(TurboFoxCfg {
+                initial_available_buffers: 0,
+                ..cfg(&dir)
+            });
+        }
+
+        #[test]
+        fn err_max_memory_below_buffer_size() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            assert_inv(TurboFoxCfg {
+                max_memory: 0x20,
+                ..cfg(&dir)
+            });
+        }
+
+        #[test]
+        fn err_sampled_pages_out_of_range() {
+            for ratio in [-0.1, 1.5, f32::NAN] {
+                let dir = tempfile::tempdir().expect("create tempdir");
+
+                assert_inv(TurboFoxCfg {
+                    integrity_check: IntegrityCheck::SampledPages(ratio),
+                    ..cfg(&dir)
+                });
+            }
+        }
+
+        #[test]
+        fn err_tombstone_ratio_out_of_range() {
+            for ratio in [-0.1, 1.5, f32::NAN] {
+                let dir = tempfile::tempdir().expect("create tempdir");
+
+                assert_inv(TurboFoxCfg {
+                    tombstone_ratio: Some(ratio),
+                    ..cfg(&dir)
+                });
+            }
+        }
+    }
+
+    mod open_mode {
+        use super::*;
+
+        fn create(dir: &tempfile::TempDir) {
+            let db = TurboFox::new(TurboFoxCfg {
+                open_mode: OpenMode::CreateNew,
+                ..cfg(dir)
+            })
+            .unwrap();
+            db.write(b"a", b"one").unwrap().wait().unwrap();
+        }
+
+        #[test]
+        fn ok_open_existing() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+            create(&dir);
+
+            let db = TurboFox::new(TurboFoxCfg {
+                open_mode: OpenMode::OpenExisting,
+                ..cfg(&dir)
+            })
+            .unwrap();
+            assert_eq!(db.read(b"a").unwrap(), Some(b"one".to_vec()));
+        }
+
+        #[test]
+        fn ok_truncate() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+            create(&dir);
+
+            let db = TurboFox::new(TurboFoxCfg {
+                open_mode: OpenMode::Truncate,
+                ..cfg(&dir)
+            })
+            .unwrap();
+            assert_eq!(db.read(b"a").unwrap(), None);
+            assert_eq!(db.keys().count(), 0);
+        }
+
+        #[test]
+        fn err_truncate_open_db() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+            let db = TurboFox::new(cfg(&dir)).unwrap();
+            db.write(b"a", b"one").unwrap().wait().unwrap();
+
+            let err = TurboFox::new(TurboFoxCfg {
+                open_mode: OpenMode::Truncate,
+                ..cfg(&dir)
+            })
+            .unwrap_err();
+            assert!(error::is_err(&err, &error::OPN));
+
+            // none of the files was pulled from under the open db
+            assert!(DB_FILES.iter().all(|name| dir.path().join(name).exists()));
+            assert_eq!(db.read(b"a").unwrap(), Some(b"one".to_vec()));
+        }
+
+        #[test]
+        fn ok_truncate_missing() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            let db = TurboFox::new(TurboFoxCfg {
+                open_mode: OpenMode::Truncate,
+                ..cfg(&dir)
+            })
+            .unwrap();
+            assert_eq!(db.keys().count(), 0);
+        }
+
+        #[test]
+        fn err_create_new_existing() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+            create(&dir);
+
+            let err = TurboFox::new(TurboFoxCfg {
+                open_mode: OpenMode::CreateNew,
+                ..cfg(&dir)
+            })
+            .unwrap_err();
+            assert!(error::is_err(&err, &error::OPN));
+        }
+
+        #[test]
+        fn err_create_new_w_leftover_files() {
+            for name in DB_FILES {
+                let dir = tempfile::tempdir().expect("create tempdir");
+                std::fs::write(dir.path().join(name), []).unwrap();
+
+                let err = TurboFox::new(TurboFoxCfg {
+                    open_mode: OpenMode::CreateNew,
+                    ..cfg(&dir)
+                })
+                .unwrap_err();
+                assert!(error::is_err(&err, &error::OPN));
+            }
+        }
+
+        #[test]
+        fn err_open_existing_missing() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            let err = TurboFox::new(TurboFoxCfg {
+                open_mode: OpenMode::OpenExisting,
+                ..cfg(&dir)
+            })
+            .unwrap_err();
+            assert!(error::is_err(&err, &error::OPN));
+            assert!(!dir.path().join("meta").exists());
+        }
+    }
+
+    mod integrity_check {
+        use super::*;
+
+        #[test]
+        fn ok_page_stride() {
+            assert_eq!(IntegrityCheck::Headers.page_stride(), None);
+            assert_eq!(IntegrityCheck::SampledPages(0.0).page_stride(), None);
+            assert_eq!(IntegrityCheck::SampledPages(0.3).page_stride(), Some(3));
+            assert_eq!(IntegrityCheck::SampledPages(1.0).page_stride(), Some(1));
+            assert_eq!(IntegrityCheck::Full.page_stride(), Some(1));
+        }
+
+        #[test]
+        fn ok_reopen_verified() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            let cfg = cfg(&dir);
+
+            {
+                let db = TurboFox::new(cfg.clone()).unwrap();
+
+                for i in 0..0x80u8 {
+                    db.write(&key(i), &[i]).unwrap();
+                }
+
+                db.delete(&key(0)).unwrap();
+            }
+
+            for integrity_check in [IntegrityCheck::SampledPages(0.5), IntegrityCheck::Full] {
+                let db = TurboFox::new(TurboFoxCfg {
+                    integrity_check,
+                    ..cfg.clone()
+                })
+                .unwrap();
+
+                assert_eq!(db.len().unwrap(), 0x7F);
+            }
+        }
+
+        #[test]
+        fn ok_verify_reports_progress() {
+            let (_dir, db) = init();
+            let pages = INIT_BUFFERS / index::ITEMS_PER_ROW;
+
+            for (check, total) in [
+                (IntegrityCheck::Headers, 0),
+                (IntegrityCheck::SampledPages(0.5), pages / 2),
+                (IntegrityCheck::Full, pages),
+            ] {
+                let mut calls = Vec::new();
+                db.verify(check, |done, total| calls.push((done, total))).unwrap();
+
+                let expected: Vec<_> = (1..=total).map(|done| (done, total)).collect();
+                assert_eq!(calls, expected);
+            }
+        }
+
+        #[test]
+        fn err_verify_torn_entry() {
+            let (_dir, db) = init();
+
+            db.write(&key(1), b"one").unwrap().wait().unwrap();
+
+            let mut torn = [0u8; 0x10];
+            torn[0] = 1;
+            db.index.tear_entry(torn);
+
+            let mut verified = 0;
+            let err = db.verify(IntegrityCheck::Full, |done, _| verified = done).unwrap_err();
+
+            assert!(error::is_err(&err, &error::CPT));
+            assert!(verified < INIT_BUFFERS / index::ITEMS_PER_ROW);
+        }
+    }
+
+    mod warmup {
+        use super::*;
+
+        #[test]
+        fn ok_reports_progress() {
+            let (_dir, db) = init();
+            let mut calls = Vec::new();
+
+            db.write(b"abc", b"one").unwrap().wait().unwrap();
+            db.warmup(|done, total| calls.push((done, total)));
+
+            let total = INIT_BUFFERS / index::ITEMS_PER_ROW;
+            let expected: Vec<_> = (1..=total).map(|done| (done, total)).collect();
+
+            assert_eq!(calls, expected);
+            assert_eq!(db.read(b"abc").unwrap(), Some(b"one".to_vec()));
+        }
+    }
+
+    mod model {
+        use super::*;
+        use std::collections::HashMap;
+
+        fn rand(state: &mut u64) -> u64 {
+            *state ^= *state << 13;
+            *state ^= *state >> 7;
+            *state ^= *state << 17;
+            *state
+        }
+
+        fn model_key(id: u64) -> Vec<u8> {
+            (id as u16).to_le_bytes().to_vec()
+        }
+
+        fn assert_eq_model(db: &TurboFox, model: &HashMap<Vec<u8>, Vec<u8>>, key_space: u64) {
+            for id in 0..key_space {
+                let k = model_key(id);
+                assert_eq!(db.read(&k).unwrap(), model.get(&k).cloned());
+            }
+
+            let mut keys: Vec<_> = db.keys().collect::<FrozenResult<_>>().unwrap();
+            let mut expected: Vec<_> = model
+                .keys()
+                .map(|k| {
+                    let mut padded = [0u8; 0x10];
+                    padded[..k.len()].copy_from_slice(k);
+                    padded
+                })
+                .collect();
+
+            keys.sort_unstable();
+            expected.sort_unstable();
+            assert_eq!(keys, expected);
+        }
+
+        /// Run `ops` random ops over `key_space` keys (half of them written up front) against a
+        /// [`HashMap`], comparing the full model every `check_every` ops and after a reopen
+        fn run(cfg: TurboFoxCfg, key_space: u64, ops: usize, check_every: usize) {
+            let mut model = HashMap::new();
+            let mut rng = 0xDEADC0DEDEADC0DEu64;
+
+            {
+                let db = TurboFox::new(cfg.clone()).unwrap();
+
+                let mut ticket = None;
+
+                for id in 0..key_space / 2 {
+                    ticket = Some(db.write(&model_key(id), &[0xFF]).unwrap());
+                    model.insert(model_key(id), vec![0xFF]);
+                }
+
+                if let Some(ticket) = ticket {
+                    ticket.wait().unwrap();
+                }
+
+                for op in 0..ops {
+                    let k = model_key(rand(&mut rng) % key_space);
+                    let len = 1 + (rand(&mut rng) % 0x20) as usize;
+                    let value = vec![op as u8; len];
+
+                    match rand(&mut rng) % 0x20 {
+                        0..=8 => {
+                            db.write(&k, &value).unwrap().wait().unwrap();
+                            model.insert(k.clone(), value);
+                        }
+
+                        9..=11 => {
+                            let applied = db.write_if_absent(&k, &value).unwrap();
+                            assert_eq!(applied.is_some(), !model.contains_key(&k), "op {op}");
+
+                            if let Some(ticket) = applied {
+                                ticket.wait().unwrap();
+                                model.insert(k.clone(), value);
+                            }
+                        }
+
+                        12..=14 => {
+                            let applied = db.write_if_present(&k, &value).unwrap();
+                            assert_eq!(applied.is_some(), model.contains_key(&k), "op {op}");
+
+                            if let Some(ticket) = applied {
+                                ticket.wait().unwrap();
+                                model.insert(k.clone(), value);
+                            }
+                        }
+
+                        15..=23 => {
+                            db.delete(&k).unwrap();
+                            model.remove(&k);
+                        }
+
+                        0x18 => db.compact().unwrap(),
+
+                        // read
+                        _ => {}
+                    }
+
+                    assert_eq!(db.read(&k).unwrap(), model.get(&k).cloned(), "op {op}");
+
+                    if op % check_every == 0 {
+                        assert_eq_model(&db, &model, key_space);
+                    }
+                }
+
+                assert_eq_model(&db, &model, key_space);
+            }
+
+            let db = TurboFox::new(cfg).unwrap();
+            assert_eq_model(&db, &model, key_space);
+        }
+
+        /// Random op sequences must behave exactly like a [`HashMap`], before and after reopen
+        #[test]
+        fn ok_matches_hashmap() {
+            let dir = tempfile::tempdir().expect("create tempdir");
+
+            let cfg = TurboFoxCfg {
+                bloom_filter: true,
+                ..cfg(&dir)
+            };
+
+            run(cfg, 0x40, 0x800, 0x100);
+        }
+
+        /// Same as [`ok_matches_hashmap`], but w/ all the keys sharing one home page, so it
+        /// overflows into the next pages and freed slots keep getting reused
+        #[test]
+        fn ok_matches_hashmap_w_colliding_keys() {
+            // distinct hashes (so lookups stay cheap), all mapping to the same index page
+            fn collide(_seed: u64, key: &[u8]) -> u64 {
+                let pages = (INIT_BUFFERS / index::ITEMS_PER_ROW) as u64;
+                0x10 + pages * key[0] as u64
+            }
+
+            for tombstone_ratio in [None, Some(0.0)] {
+                let dir = tempfile::tempdir().expect("create tempdir");
+
+                let cfg = TurboFoxCfg {
+                    tombstone_ratio,
+                    hash_fn: HashFn::Custom(collide),
+                    ..cfg(&dir)
+                };
+
+                // NOTE: ~57% of the keys stay live, which keeps the home page overflowed
+                run(cfg, (index::ITEMS_PER_ROW * 2) as u64, 0x300, 0x40);
+            }
+        }
     }
 
     mod stress {
@@ -511,5 +2248,52 @@ mod tests {
                 assert_eq!(db.read(&key(i)).unwrap(), Some(value));
             }
         }
+
+        #[test]
+        fn ok_concurrent_self_verifying() {
+            const WRITERS: u8 = 4;
+            const READERS: u8 = 4;
+            const KEYS_PER_WRITER: u8 = 0x10;
+            const ROUNDS: u8 = 0x08;
+
+            let (_dir, db) = init();
+
+            // value embeds its key, so a reader can detect values served for the wrong key
+            let value = |id: u8, round: u8| [id, round].repeat(0x10);
+
+            std::thread::scope(|s| {
+                for w in 0..WRITERS {
+                    let db = &db;
+
+                    s.spawn(move || {
+                        for round in 0..ROUNDS {
+                            for i in 0..KEYS_PER_WRITER {
+                                let id = w * KEYS_PER_WRITER + i;
+                                db.write(&key(id), &value(id, round)).unwrap().wait().unwrap();
+                            }
+                        }
+                    });
+                }
+
+                for _ in 0..READERS {
+                    let db = &db;
+
+                    s.spawn(move || {
+                        for _ in 0..ROUNDS {
+                            for id in 0..WRITERS * KEYS_PER_WRITER {
+                                if let Some(data) = db.read(&key(id)).unwrap() {
+                                    assert_eq!(data.len(), 0x20);
+                                    assert!(data.chunks(2).all(|c| c[0] == id && c[1] == data[1]));
+                                }
+                            }
+                        }
+                    });
+                }
+            });
+
+            for id in 0..WRITERS * KEYS_PER_WRITER {
+                assert_eq!(db.read(&key(id)).unwrap(), Some(value(id, ROUNDS - 1)));
+            }
+        }
     }
 }
@@ -0,0 +1,265 @@
+use crate::{HashFn, MODULE_ID};
+use frozen_core::{error, fmmap};
+use std::{hash::BuildHasher, path, time};
+
+/// Magic bytes identifying the `meta` file of [`crate::TurboFox`]
+const MAGIC: u64 = u64::from_le_bytes(*b"TRBOFOX\0");
+
+/// Version of the on-disk format, bumped on any layout change of the db files
+pub(crate) const VERSION: u64 = 0x03;
+
+/// Geometry of the db files, persisted on creation and validated on every open
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Meta {
+    magic: u64,
+    pub(crate) version: u64,
+    pub(crate) buffer_size: u64,
+    pub(crate) items_per_row: u64,
+    pub(crate) hash_fn: u64,
+
+    /// Capacity the db files were created w/, which can't be grown on reopen
+    pub(crate) initial_available_buffers: u64,
+
+    /// Per-db random seed for the index hashes, so keys can't be crafted to collide in advance
+    pub(crate) seed: u64,
+}
+
+impl Meta {
+    pub(crate) fn new(
+        buffer_size: usize,
+        items_per_row: usize,
+        hash_fn: HashFn,
+        initial_available_buffers: usize,
+    ) -> Self {
+        Self {
+            magic: MAGIC,
+            version: VERSION,
+            buffer_size: buffer_size as u64,
+            items_per_row: items_per_row as u64,
+            hash_fn: hash_fn.tag(),
+            initial_available_buffers: initial_available_buffers as u64,
+            seed: random_seed(),
+        }
+    }
+
+    /// Read the persisted [`Meta`] from the `meta` file at `path`
+    ///
+    /// Returns `None` when the file is missing or was never written to, in which case the file
+    /// is left untouched (i.e. it's never created here)
+    pub(crate) fn load<P: AsRef<path::Path>>(
+        path: P,
+        flush_duration: time::Duration,
+    ) -> error::FrozenResult<Option<Self>> {
+        if !path.as_ref().exists() {
+            return Ok(None);
+        }
+
+        let mmap = fmmap::FrozenMMap::<Meta>::new(path.as_ref(), mmap_cfg(flush_duration))?;
+        let on_disk = unsafe { mmap.read(0, |raw_meta| *raw_meta) };
+
+        // NOTE: a zeroed out meta means the file was created but never written to
+        if on_disk.magic == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(on_disk))
+    }
+
+    /// Durably write `self` into the `meta` file at `path`, creating it if needed
+    pub(crate) fn persist<P: AsRef<path::Path>>(
+        &self,
+        path: P,
+        flush_duration: time::Duration,
+    ) -> error::FrozenResult<()> {
+        let mmap = fmmap::FrozenMMap::<Meta>::new(path.as_ref(), mmap_cfg(flush_duration))?;
+        let ticket = unsafe { mmap.write(0, |raw_meta| *raw_meta = *self) }?;
+        ticket.wait()?;
+
+        Ok(())
+    }
+
+    /// Validate the persisted [`Meta`] against the `requested` one, where the `seed` is never
+    /// validated as it's only generated once on creation
+    ///
+    /// A smaller `initial_available_buffers` is accepted, as the persisted capacity is kept
+    pub(crate) fn validate(&self, requested: &Meta) -> error::FrozenResult<()> {
+        if self.magic != MAGIC {
+            return crate::error::new_err(crate::error::CPT, "invalid magic in meta file");
+        }
+
+        let fields = [
+            ("version", self.version, requested.version),
+            ("buffer_size", self.buffer_size, requested.buffer_size),
+            ("items_per_row", self.items_per_row, requested.items_per_row),
+            ("hash_fn", self.hash_fn, requested.hash_fn),
+        ];
+
+        for (field, on_disk, requested) in fields {
+            if on_disk != requested {
+                let errmsg = format!("{field}: on disk {on_disk}, requested {requested}");
+                return crate::error::new_err(crate::error::CFG, &errmsg);
+            }
+        }
+
+        if self.initial_available_buffers < requested.initial_available_buffers {
+            let errmsg = format!(
+                "initial_available_buffers: on disk {}, requested {}",
+                self.initial_available_buffers, requested.initial_available_buffers
+            );
+            return crate::error::new_err(crate::error::CFG, &errmsg);
+        }
+
+        Ok(())
+    }
+}
+
+fn mmap_cfg(flush_duration: time::Duration) -> fmmap::FrozenMMapCfg {
+    fmmap::FrozenMMapCfg {
+        flush_duration,
+        module_id: MODULE_ID,
+        initial_count: 1,
+        immediate_durability: true,
+    }
+}
+
+/// Generate a random seed w/o depending on an external RNG, as [`std::hash::RandomState`] is
+/// randomly keyed per process
+fn random_seed() -> u64 {
+    std::hash::RandomState::new().hash_one(time::SystemTime::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLUSH_DURATION: time::Duration = time::Duration::from_millis(1);
+
+    fn meta(buffer_size: usize, hash_fn: HashFn) -> Meta {
+        Meta::new(buffer_size, 0x100, hash_fn, 0x1000)
+    }
+
+    fn open(path: &path::Path, requested: Meta) -> error::FrozenResult<Meta> {
+        match Meta::load(path, FLUSH_DURATION)? {
+            Some(on_disk) => on_disk.validate(&requested).map(|_| on_disk),
+            None => requested.persist(path, FLUSH_DURATION).map(|_| requested),
+        }
+    }
+
+    #[test]
+    fn ok_create_and_reopen() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("meta");
+        let created = meta(0x40, HashFn::XxHash64);
+
+        assert_eq!(open(&path, created).unwrap(), created);
+
+        // persisted seed wins over the newly generated one
+        let reopened = open(&path, meta(0x40, HashFn::XxHash64)).unwrap();
+        assert_eq!(reopened, created);
+    }
+
+    #[test]
+    fn ok_load_missing() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("meta");
+
+        assert_eq!(Meta::load(&path, FLUSH_DURATION).unwrap(), None);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn ok_load_zeroed() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("meta");
+
+        std::fs::write(&path, [0u8; std::mem::size_of::<Meta>()]).unwrap();
+        assert_eq!(Meta::load(&path, FLUSH_DURATION).unwrap(), None);
+    }
+
+    #[test]
+    fn ok_random_seed_per_db() {
+        let seeds: std::collections::HashSet<u64> = (0..0x10)
+            .map(|_| meta(0x40, HashFn::XxHash64).seed)
+            .collect();
+
+        assert_eq!(seeds.len(), 0x10);
+    }
+
+    #[test]
+    fn err_mismatched_buffer_size() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("meta");
+
+        open(&path, meta(0x40, HashFn::XxHash64)).unwrap();
+
+        let err = open(&path, meta(0x80, HashFn::XxHash64)).unwrap_err();
+        assert!(crate::error::is_err(&err, &crate::error::CFG));
+        assert!(
+            err.context
+                .contains("buffer_size: on disk 64, requested 128")
+        );
+    }
+
+    #[test]
+    fn err_mismatched_hash_fn() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("meta");
+
+        open(&path, meta(0x40, HashFn::XxHash64)).unwrap();
+
+        let err = open(&path, meta(0x40, HashFn::Fnv1a)).unwrap_err();
+        assert!(crate::error::is_err(&err, &crate::error::CFG));
+        assert!(err.context.contains("hash_fn"));
+    }
+
+    #[test]
+    fn err_mismatched_version() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("meta");
+
+        let old = Meta {
+            version: VERSION + 1,
+            ..meta(0x40, HashFn::XxHash64)
+        };
+        open(&path, old).unwrap();
+
+        let err = open(&path, meta(0x40, HashFn::XxHash64)).unwrap_err();
+        assert!(crate::error::is_err(&err, &crate::error::CFG));
+        assert!(err.context.contains("version"));
+    }
+
+    #[test]
+    fn ok_smaller_capacity() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("meta");
+        let created = meta(0x40, HashFn::XxHash64);
+
+        open(&path, created).unwrap();
+
+        let smaller = Meta {
+            initial_available_buffers: 0x100,
+            ..meta(0x40, HashFn::XxHash64)
+        };
+        assert_eq!(open(&path, smaller).unwrap(), created);
+    }
+
+    #[test]
+    fn err_larger_capacity() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("meta");
+
+        open(&path, meta(0x40, HashFn::XxHash64)).unwrap();
+
+        let larger = Meta {
+            initial_available_buffers: 0x2000,
+            ..meta(0x40, HashFn::XxHash64)
+        };
+        let err = open(&path, larger).unwrap_err();
+        assert!(crate::error::is_err(&err, &crate::error::CFG));
+        assert!(
+            err.context
+                .contains("initial_available_buffers: on disk 4096, requested 8192")
+        );
+    }
+}
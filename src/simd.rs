@@ -0,0 +1,139 @@
+//! Lane-wise comparisons used to scan the hash rows of [`crate::index::Index`]
+//!
+//! On `x86_64`, SSE2 is used by default and AVX2 when the crate is built w/ `target_feature=+avx2`,
+//! all other targets, and builds w/ `--cfg turbofox_scalar`, use the portable scalar fallback.
+
+/// Number of `u64` hashes compared in a single [`eq_mask`] call
+pub(crate) const LANES: usize = 0x10;
+
+/// Compare every lane in `group` against `needle`
+///
+/// Returns a bitmask where bit `i` is set iff `group[i] == needle`
+#[inline(always)]
+#[cfg(all(target_arch = "x86_64", target_feature = "avx2", not(turbofox_scalar)))]
+pub(crate) fn eq_mask(group: &[u64; LANES], needle: u64) -> u16 {
+    use std::arch::x86_64::*;
+
+    unsafe {
+        let probe = _mm256_set1_epi64x(needle as i64);
+        let ptr = group.as_ptr() as *const __m256i;
+        let mut mask = 0u16;
+
+        for i in 0..(LANES / 4) {
+            let lanes = _mm256_loadu_si256(ptr.add(i));
+            let eq = _mm256_cmpeq_epi64(lanes, probe);
+
+            mask |= (_mm256_movemask_pd(_mm256_castsi256_pd(eq)) as u16) << (i * 4);
+        }
+
+        mask
+    }
+}
+
+/// Compare every lane in `group` against `needle`
+///
+/// Returns a bitmask where bit `i` is set iff `group[i] == needle`
+#[inline(always)]
+#[cfg(all(target_arch = "x86_64", not(target_feature = "avx2"), not(turbofox_scalar)))]
+pub(crate) fn eq_mask(group: &[u64; LANES], needle: u64) -> u16 {
+    use std::arch::x86_64::*;
+
+    unsafe {
+        let probe = _mm_set1_epi64x(needle as i64);
+        let ptr = group.as_ptr() as *const __m128i;
+        let mut mask = 0u16;
+
+        for i in 0..(LANES / 2) {
+            let lanes = _mm_loadu_si128(ptr.add(i));
+
+            // NOTE: SSE2 has no 64-bit compare, so a lane is equal only when both of its 32-bit
+            // halves are equal, i.e. `eq32 & swap_halves(eq32)`
+            let eq32 = _mm_cmpeq_epi32(lanes, probe);
+            let eq = _mm_and_si128(eq32, _mm_shuffle_epi32::<0b10_11_00_01>(eq32));
+
+            mask |= (_mm_movemask_pd(_mm_castsi128_pd(eq)) as u16) << (i * 2);
+        }
+
+        mask
+    }
+}
+
+/// Compare every lane in `group` against `needle`
+///
+/// Returns a bitmask where bit `i` is set iff `group[i] == needle`
+#[inline(always)]
+#[cfg(any(not(target_arch = "x86_64"), turbofox_scalar))]
+pub(crate) fn eq_mask(group: &[u64; LANES], needle: u64) -> u16 {
+    scalar_eq_mask(group, needle)
+}
+
+#[inline(always)]
+#[cfg_attr(all(target_arch = "x86_64", not(turbofox_scalar)), allow(dead_code))]
+fn scalar_eq_mask(group: &[u64; LANES], needle: u64) -> u16 {
+    let mut mask = 0u16;
+
+    for (i, lane) in group.iter().enumerate() {
+        mask |= ((*lane == needle) as u16) << i;
+    }
+
+    mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ok_no_match() {
+        let group = [7u64; LANES];
+
+        assert_eq!(eq_mask(&group, 8), 0);
+    }
+
+    #[test]
+    fn ok_all_match() {
+        let group = [7u64; LANES];
+
+        assert_eq!(eq_mask(&group, 7), u16::MAX);
+    }
+
+    #[test]
+    fn ok_single_lane() {
+        for i in 0..LANES {
+            let mut group = [0u64; LANES];
+            group[i] = 0xDEADC0DEDEADC0DE;
+
+            assert_eq!(eq_mask(&group, 0xDEADC0DEDEADC0DE), 1 << i);
+        }
+    }
+
+    #[test]
+    fn ok_partial_half_is_not_a_match() {
+        let mut group = [0u64; LANES];
+        group[0] = 0x0000_0001_0000_0002;
+        group[1] = 0x0000_0002_0000_0001;
+        group[2] = 0x0000_0002_0000_0002;
+
+        assert_eq!(eq_mask(&group, 0x0000_0002_0000_0002), 0b100);
+    }
+
+    #[test]
+    fn ok_matches_scalar() {
+        let mut rng = 0xDEADBEEFCAFEBABEu64;
+        let mut group = [0u64; LANES];
+
+        for _ in 0..0x400 {
+            for lane in group.iter_mut() {
+                rng ^= rng << 13;
+                rng ^= rng >> 7;
+                rng ^= rng << 17;
+
+                *lane = rng % 4;
+            }
+
+            for needle in 0..4 {
+                assert_eq!(eq_mask(&group, needle), scalar_eq_mask(&group, needle));
+            }
+        }
+    }
+}